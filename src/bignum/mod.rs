@@ -0,0 +1,8 @@
+pub mod rational;
+pub mod uint;
+
+#[cfg(test)]
+mod tests;
+
+pub use rational::BigRational;
+pub use uint::BigUint;
@@ -0,0 +1,160 @@
+use std::fmt::Display;
+use std::ops::{Add, Mul};
+
+use num_traits::{One, Zero};
+
+use super::BigUint;
+
+// non-negative fraction kept in lowest terms
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct BigRational {
+    num: BigUint,
+    den: BigUint,
+}
+
+impl BigRational {
+    pub fn new(num: BigUint, den: BigUint) -> Self {
+        assert!(!den.is_zero(), "Null denominator");
+        let gcd = num.gcd(&den);
+        if gcd.is_one() {
+            BigRational { num, den }
+        } else {
+            BigRational {
+                num: num.div_rem(&gcd).0,
+                den: den.div_rem(&gcd).0,
+            }
+        }
+    }
+
+    pub fn numer(&self) -> &BigUint {
+        &self.num
+    }
+
+    pub fn denom(&self) -> &BigUint {
+        &self.den
+    }
+
+    // floats are dyadic so the conversion is exact
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        if value == 0.0 {
+            return Some(Self::zero());
+        }
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7FF) as i64;
+        let mantissa = if exponent == 0 {
+            (bits & ((1 << 52) - 1)) << 1
+        } else {
+            (bits & ((1 << 52) - 1)) | (1 << 52)
+        };
+        let shift = exponent - 1075;
+        let mantissa = BigUint::from(mantissa);
+        Some(if shift >= 0 {
+            BigRational::new(&mantissa << shift as usize, BigUint::one())
+        } else {
+            BigRational::new(mantissa, BigUint::pow2((-shift) as usize))
+        })
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        // keep 64 significant bits in the quotient
+        let shift = (self.den.bits() + 64).saturating_sub(self.num.bits());
+        let (quotient, _) = (&self.num << shift).div_rem(&self.den);
+        let mut value = quotient.to_f64();
+        let mut shift = shift;
+        while shift > 0 {
+            let step = shift.min(1000);
+            value /= 2f64.powi(step as i32);
+            shift -= step;
+        }
+        value
+    }
+}
+
+impl From<BigUint> for BigRational {
+    fn from(value: BigUint) -> Self {
+        BigRational {
+            num: value,
+            den: BigUint::one(),
+        }
+    }
+}
+
+impl PartialOrd for BigRational {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigRational {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.num * &other.den).cmp(&(&other.num * &self.den))
+    }
+}
+
+impl Zero for BigRational {
+    fn zero() -> Self {
+        BigRational::from(BigUint::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.num.is_zero()
+    }
+}
+
+impl One for BigRational {
+    fn one() -> Self {
+        BigRational::from(BigUint::one())
+    }
+}
+
+impl<'a> Add<&'a BigRational> for &'a BigRational {
+    type Output = BigRational;
+
+    fn add(self, other: &BigRational) -> BigRational {
+        if self.den == other.den {
+            BigRational::new(&self.num + &other.num, self.den.clone())
+        } else {
+            BigRational::new(
+                &(&self.num * &other.den) + &(&other.num * &self.den),
+                &self.den * &other.den,
+            )
+        }
+    }
+}
+
+impl Add for BigRational {
+    type Output = BigRational;
+
+    fn add(self, other: BigRational) -> BigRational {
+        &self + &other
+    }
+}
+
+impl<'a> Mul<&'a BigRational> for &'a BigRational {
+    type Output = BigRational;
+
+    fn mul(self, other: &BigRational) -> BigRational {
+        BigRational::new(&self.num * &other.num, &self.den * &other.den)
+    }
+}
+
+impl Mul for BigRational {
+    type Output = BigRational;
+
+    fn mul(self, other: BigRational) -> BigRational {
+        &self * &other
+    }
+}
+
+impl Display for BigRational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den.is_one() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
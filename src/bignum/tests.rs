@@ -0,0 +1,62 @@
+use num_traits::{One, Zero};
+
+use super::*;
+
+#[test]
+fn uint_arithmetic() {
+    let a = BigUint::from(u128::MAX);
+    let b = BigUint::from(12345678901234567890u64);
+
+    assert_eq!(
+        (&a + &b).checked_sub(&b).unwrap().to_u128(),
+        Some(u128::MAX)
+    );
+    assert_eq!(
+        (&BigUint::from(123456789u64) * &b).to_u128(),
+        Some(123456789u128 * 12345678901234567890u128)
+    );
+    assert!(b.checked_sub(&a).is_none());
+    assert!(BigUint::zero() < BigUint::one());
+    assert!(b < a);
+
+    assert_eq!(
+        format!("{}", BigUint::pow2(200)),
+        "1606938044258990275541962092341162602522202993782792835301376"
+    );
+    assert_eq!(format!("{}", BigUint::zero()), "0");
+    assert_eq!(&BigUint::pow2(100) >> 90, BigUint::from(1024u64));
+    assert_eq!(
+        &BigUint::from(3u64) << 128,
+        &BigUint::pow2(129) + &BigUint::pow2(128)
+    );
+
+    let three_pow = (0..50).fold(BigUint::one(), |acc, _| &acc * &BigUint::from(3u64));
+    let large = &(&BigUint::pow2(100) + &BigUint::from(7u64)) * &three_pow;
+    assert_eq!(
+        format!("{large}"),
+        "910043815000214977332758527539281918406558228447050367"
+    );
+    let (quotient, remainder) = large.div_rem(&three_pow);
+    assert_eq!(quotient, &BigUint::pow2(100) + &BigUint::from(7u64));
+    assert!(remainder.is_zero());
+    assert_eq!(large.gcd(&(&three_pow * &BigUint::from(2u64))), three_pow);
+
+    assert!((BigUint::pow2(300).to_f64() - 2f64.powi(300)).abs() < 1e75);
+}
+
+#[test]
+fn rational_arithmetic() {
+    let half = BigRational::from_f64(0.5).unwrap();
+    let quarter = BigRational::from_f64(0.25).unwrap();
+    let third = BigRational::new(BigUint::from(2u64), BigUint::from(6u64));
+
+    assert_eq!(format!("{third}"), "1/3");
+    assert_eq!(&half + &quarter, BigRational::from_f64(0.75).unwrap());
+    assert_eq!(&half * &half, quarter);
+    assert_eq!(format!("{}", &third + &third), "2/3");
+    assert_eq!(&third + &(&third + &third), BigRational::one());
+    assert!(quarter < third && third < half);
+    assert_eq!(BigRational::from_f64(0.1).unwrap().to_f64(), 0.1);
+    assert_eq!(BigRational::from_f64(3e200).unwrap().to_f64(), 3e200);
+    assert!(BigRational::from_f64(-1.0).is_none());
+}
@@ -0,0 +1,349 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Shl, Shr, Sub};
+
+use num_traits::{One, Zero};
+
+// arbitrary precision natural number, little-endian limbs without trailing zeros
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn pow2(exp: usize) -> Self {
+        let mut limbs = vec![0u32; exp / 32 + 1];
+        limbs[exp / 32] = 1 << (exp % 32);
+        BigUint { limbs }
+    }
+
+    pub fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(last) => 32 * self.limbs.len() - last.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn bit(&self, idx: usize) -> bool {
+        self.limbs
+            .get(idx / 32)
+            .is_some_and(|limb| (limb >> (idx % 32)) & 1 != 0)
+    }
+
+    pub fn trailing_zeros(&self) -> usize {
+        match self.limbs.iter().position(|&limb| limb != 0) {
+            Some(idx) => 32 * idx + self.limbs[idx].trailing_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+        Some(
+            self.limbs
+                .iter()
+                .rev()
+                .fold(0u128, |acc, &limb| (acc << 32) | limb as u128),
+        )
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let bits = self.bits();
+        if bits <= 128 {
+            self.to_u128().unwrap() as f64
+        } else {
+            let shift = bits - 64;
+            let top = (self >> shift).to_u128().unwrap();
+            top as f64 * 2f64.powi(shift.min(i32::MAX as usize) as i32)
+        }
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut limbs = self.limbs.clone();
+        let mut borrow = 0i64;
+        for (idx, limb) in limbs.iter_mut().enumerate() {
+            let rhs = other.limbs.get(idx).copied().unwrap_or(0) as i64;
+            let mut diff = *limb as i64 - rhs - borrow;
+            borrow = if diff < 0 {
+                diff += 1 << 32;
+                1
+            } else {
+                0
+            };
+            *limb = diff as u32;
+        }
+        Some(BigUint { limbs }.normalized())
+    }
+
+    pub fn div_rem(&self, other: &Self) -> (Self, Self) {
+        assert!(!other.is_zero(), "Division by zero");
+        if *self < *other {
+            return (Self::zero(), self.clone());
+        }
+        if other.limbs.len() == 1 {
+            let (quotient, remainder) = self.div_rem_small(other.limbs[0]);
+            return (quotient, BigUint::from(remainder as u64));
+        }
+
+        // shift and subtract, one bit at a time
+        let mut quotient = BigUint {
+            limbs: vec![0u32; self.limbs.len()],
+        };
+        let mut remainder = Self::zero();
+        for idx in (0..self.bits()).rev() {
+            remainder = &remainder << 1;
+            if self.bit(idx) {
+                if remainder.limbs.is_empty() {
+                    remainder.limbs.push(1);
+                } else {
+                    remainder.limbs[0] |= 1;
+                }
+            }
+            if remainder >= *other {
+                remainder = remainder.checked_sub(other).unwrap();
+                quotient.limbs[idx / 32] |= 1 << (idx % 32);
+            }
+        }
+        (quotient.normalized(), remainder)
+    }
+
+    fn div_rem_small(&self, divisor: u32) -> (Self, u32) {
+        let mut limbs = self.limbs.clone();
+        let mut remainder = 0u64;
+        for limb in limbs.iter_mut().rev() {
+            let current = (remainder << 32) | *limb as u64;
+            *limb = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+        (BigUint { limbs }.normalized(), remainder as u32)
+    }
+
+    // binary gcd, only needs shifts and subtractions
+    pub fn gcd(&self, other: &Self) -> Self {
+        if self.is_zero() {
+            return other.clone();
+        }
+        if other.is_zero() {
+            return self.clone();
+        }
+        let shift = self.trailing_zeros().min(other.trailing_zeros());
+        let mut a = self >> self.trailing_zeros();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            b = &b >> b.trailing_zeros();
+            if a > b {
+                std::mem::swap(&mut a, &mut b);
+            }
+            b = b.checked_sub(&a).unwrap();
+        }
+        &a << shift
+    }
+
+    fn normalized(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        BigUint::from(value as u128)
+    }
+}
+
+impl From<usize> for BigUint {
+    fn from(value: usize) -> Self {
+        BigUint::from(value as u128)
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(value: u128) -> Self {
+        BigUint {
+            limbs: (0..4).map(|idx| (value >> (32 * idx)) as u32).collect(),
+        }
+        .normalized()
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl Zero for BigUint {
+    fn zero() -> Self {
+        BigUint { limbs: Vec::new() }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+}
+
+impl One for BigUint {
+    fn one() -> Self {
+        BigUint { limbs: vec![1] }
+    }
+}
+
+impl<'a> Add<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut limbs = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry = 0u64;
+        for (idx, &limb) in long.limbs.iter().enumerate() {
+            let sum = limb as u64 + short.limbs.get(idx).copied().unwrap_or(0) as u64 + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        if carry != 0 {
+            limbs.push(carry as u32);
+        }
+        BigUint { limbs }
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        &self + &other
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        *self = &*self + other;
+    }
+}
+
+impl Sub for BigUint {
+    type Output = BigUint;
+
+    fn sub(self, other: BigUint) -> BigUint {
+        self.checked_sub(&other).expect("Subtraction underflow")
+    }
+}
+
+impl<'a> Mul<&'a BigUint> for &'a BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let current = limbs[i + j] as u64 + a as u64 * b as u64 + carry;
+                limbs[i + j] = current as u32;
+                carry = current >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint { limbs }.normalized()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        &self * &other
+    }
+}
+
+impl MulAssign<&BigUint> for BigUint {
+    fn mul_assign(&mut self, other: &BigUint) {
+        *self = &*self * other;
+    }
+}
+
+impl Shl<usize> for &BigUint {
+    type Output = BigUint;
+
+    fn shl(self, shift: usize) -> BigUint {
+        if self.is_zero() {
+            return BigUint::zero();
+        }
+        let (limb_shift, bit_shift) = (shift / 32, shift % 32);
+        let mut limbs = vec![0u32; limb_shift];
+        let mut carry = 0u32;
+        for &limb in self.limbs.iter() {
+            if bit_shift == 0 {
+                limbs.push(limb);
+            } else {
+                limbs.push((limb << bit_shift) | carry);
+                carry = limb >> (32 - bit_shift);
+            }
+        }
+        limbs.push(carry);
+        BigUint { limbs }.normalized()
+    }
+}
+
+impl Shr<usize> for &BigUint {
+    type Output = BigUint;
+
+    fn shr(self, shift: usize) -> BigUint {
+        let (limb_shift, bit_shift) = (shift / 32, shift % 32);
+        if limb_shift >= self.limbs.len() {
+            return BigUint::zero();
+        }
+        let limbs = &self.limbs[limb_shift..];
+        BigUint {
+            limbs: (0..limbs.len())
+                .map(|idx| {
+                    let high = limbs.get(idx + 1).copied().unwrap_or(0) as u64;
+                    ((((high << 32) | limbs[idx] as u64) >> bit_shift) & 0xFFFF_FFFF) as u32
+                })
+                .collect(),
+        }
+        .normalized()
+    }
+}
+
+impl Display for BigUint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut chunks: Vec<u32> = Vec::new();
+        let mut current = self.clone();
+        while !current.is_zero() {
+            let (quotient, remainder) = current.div_rem_small(1_000_000_000);
+            chunks.push(remainder);
+            current = quotient;
+        }
+        write!(f, "{}", chunks.last().unwrap())?;
+        for chunk in chunks.iter().rev().skip(1) {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
@@ -1 +1,2 @@
 pub mod cnf;
+pub mod wcnf;
@@ -0,0 +1,117 @@
+use std::fs;
+
+use crate::{logic::propositional::Literal, solver::maxsat::Wcnf};
+
+pub fn load_file(file_name: String) -> Result<Wcnf, &'static str> {
+    let contents = fs::read_to_string(file_name).expect("Not able to load file.");
+    load_string(contents)
+}
+
+// both the classic format, with a "p wcnf vars clauses top" line and hard
+// clauses weighted top, and the newer one where hard clauses start with "h"
+pub fn load_string(wcnf: String) -> Result<Wcnf, &'static str> {
+    let mut result = Wcnf::default();
+    let mut top: Option<u64> = None;
+    let mut num_clauses: Option<usize> = None;
+    let mut tokens: Vec<&str> = Default::default();
+
+    for line in wcnf.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        if line.starts_with('p') {
+            let config: Vec<&str> = line.split_whitespace().collect();
+            if config.len() < 4 || config[1] != "wcnf" {
+                return Err("Invalid problem line");
+            }
+            result.num_variables = config[2].parse().map_err(|_| "Invalid problem line")?;
+            num_clauses = Some(config[3].parse().map_err(|_| "Invalid problem line")?);
+            if let Some(value) = config.get(4) {
+                top = Some(value.parse().map_err(|_| "Invalid problem line")?);
+            }
+            continue;
+        }
+        tokens.extend(line.split_whitespace());
+    }
+
+    // each clause is a weight or "h" followed by literals ending with 0
+    let mut tokens = tokens.into_iter();
+    let mut count = 0;
+    while let Some(head) = tokens.next() {
+        let weight = match head {
+            "h" => None,
+            _ => {
+                let weight: u64 = head.parse().map_err(|_| "Invalid clause weight")?;
+                match top {
+                    Some(top) if weight >= top => None,
+                    _ => Some(weight),
+                }
+            }
+        };
+
+        let mut clause: Vec<Literal> = Default::default();
+        loop {
+            let Some(token) = tokens.next() else {
+                return Err("Clause is not terminated by 0");
+            };
+            let value: i32 = token.parse().map_err(|_| "Invalid literal")?;
+            if value == 0 {
+                break;
+            }
+            clause.push(Literal::from_dimacs(value));
+        }
+
+        match weight {
+            Some(weight) => result.add_soft(&clause, weight),
+            None => result.add_hard(&clause),
+        }
+        count += 1;
+    }
+
+    if num_clauses.is_some_and(|num_clauses| num_clauses != count) {
+        return Err("Inconsistent number of clauses");
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::maxsat::solve;
+
+    use super::*;
+
+    #[test]
+    fn test_wcnf_parser() {
+        let classic = load_string(
+            r#"
+c two soft units against a hard clause
+p wcnf 3 4 10
+10 -1 -2 0
+3 1 0
+4 2 0
+1 3 0
+"#
+            .to_string(),
+        )
+        .unwrap();
+        let recent = load_string(
+            r#"
+c same problem without the problem line
+h -1 -2 0
+3 1 0
+4 2 0
+1 3 0
+"#
+            .to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(classic, recent);
+        assert_eq!(classic.hard.len(), 1);
+        assert_eq!(classic.soft.len(), 3);
+        assert_eq!(solve(&classic), Ok((vec![false, true, true], 3)));
+
+        assert!(load_string("p wcnf 2 2 5\n5 1 2 0\n".to_string()).is_err());
+        assert!(load_string("h 1 2\n".to_string()).is_err());
+    }
+}
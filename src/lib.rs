@@ -1,5 +1,7 @@
+pub mod bignum;
 pub mod io;
 #[macro_use]
 pub mod logic;
+pub mod random;
 pub mod solver;
 pub mod tree;
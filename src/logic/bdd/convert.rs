@@ -0,0 +1,100 @@
+use crate::{
+    logic::{
+        circuit::{PCMut, ProbabilisticCircuitTree},
+        propositional::{PLogic, PropositionalTree},
+    },
+    tree::{Addr, IndexedMutRef, LinkingNode, Mapping},
+};
+
+use super::{Bdd, BddRef};
+
+// the manager must have at least the variables of the tree, an empty tree is false
+pub fn propositional_to_bdd(bdd: &mut Bdd, tree: &PropositionalTree) -> BddRef {
+    let mut values = vec![BddRef::FALSE; tree.num_nodes()];
+    for idx in tree.topological() {
+        let operands = tree[idx].node.operands();
+        values[idx.addr()] = match tree[idx].value {
+            PLogic::Variable { id } => bdd.literal(id, false),
+            PLogic::Not => bdd.not(values[operands[0].addr()]),
+            PLogic::And => bdd.and(values[operands[0].addr()], values[operands[1].addr()]),
+            PLogic::Or => bdd.or(values[operands[0].addr()], values[operands[1].addr()]),
+        };
+    }
+    match tree.output().idx.into() {
+        Some(output) => values.swap_remove(output),
+        None => BddRef::FALSE,
+    }
+}
+
+// every node becomes a deterministic sum on its variable, levels skipped by
+// an edge are smoothed with x∨¬x so the circuit covers all the variables; the
+// variables are named like the ones of the tree, false has no circuit
+pub fn bdd_to_circuit(
+    bdd: &Bdd,
+    f: BddRef,
+    tree: &PropositionalTree,
+) -> Result<ProbabilisticCircuitTree, &'static str> {
+    if f == BddRef::FALSE {
+        return Err("Formula is unsatisfiable");
+    }
+    let mut circuit: ProbabilisticCircuitTree = Default::default();
+    circuit.copy_named(tree);
+    for _ in tree.num_named()..bdd.num_variables() {
+        circuit.add_anon();
+    }
+    circuit.builder(|builder| build_circuit(builder, bdd, f));
+    Ok(circuit)
+}
+
+fn free(
+    builder: &mut IndexedMutRef<ProbabilisticCircuitTree>,
+    bdd: &Bdd,
+    levels: std::ops::Range<usize>,
+) -> Vec<Addr> {
+    levels
+        .map(|level| {
+            let id = bdd.order()[level];
+            builder.sum(|left| left.var(id), |right| right.not_var(id))
+        })
+        .collect()
+}
+
+fn build_circuit(
+    builder: &mut IndexedMutRef<ProbabilisticCircuitTree>,
+    bdd: &Bdd,
+    f: BddRef,
+) -> Addr {
+    // circuit of each node over the levels below it, None for the empty product
+    let mut circuits: Vec<Option<Addr>> = vec![None; bdd.num_nodes()];
+    for current in bdd.reachable(f) {
+        if current.is_constant() {
+            continue;
+        }
+        let node = bdd.node(current);
+        let id = bdd.var(current);
+        let mut branches: Vec<Addr> = Default::default();
+        for (child, neg) in [(node.high, false), (node.low, true)] {
+            if child == BddRef::FALSE {
+                continue;
+            }
+            let mut parts = vec![if neg {
+                builder.not_var(id)
+            } else {
+                builder.var(id)
+            }];
+            parts.extend(free(builder, bdd, node.level + 1..bdd.node(child).level));
+            parts.extend(circuits[child.index()]);
+            branches.push(builder.prod_n(&mut parts.iter(), |_, &part| part));
+        }
+        circuits[current.index()] = match branches[..] {
+            [high, low] => Some(builder.sum(|_| high, |_| low)),
+            [branch] => Some(branch),
+            _ => unreachable!(),
+        };
+    }
+
+    let mut parts = free(builder, bdd, 0..bdd.node(f).level);
+    parts.extend(circuits[f.index()]);
+    // the empty product of a constant function over no variable has no node
+    builder.prod_n(&mut parts.iter(), |_, &part| part)
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use crate::{bignum::BigUint, tree::Addr};
+
+// index of a node in the manager, 0 and 1 are the terminals
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BddRef(u32);
+
+impl BddRef {
+    pub const FALSE: BddRef = BddRef(0);
+    pub const TRUE: BddRef = BddRef(1);
+
+    #[inline(always)]
+    pub fn is_constant(self) -> bool {
+        self.0 < 2
+    }
+
+    #[inline(always)]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BddNode {
+    pub level: usize,
+    pub low: BddRef,
+    pub high: BddRef,
+}
+
+// reduced ordered BDDs sharing one unique table, equivalent functions
+// built in the same manager get the same reference
+#[derive(Debug, Clone)]
+pub struct Bdd {
+    order: Vec<Addr>,
+    levels: Vec<usize>,
+    nodes: Vec<BddNode>,
+    unique: HashMap<(usize, BddRef, BddRef), BddRef>,
+    computed: HashMap<(BddRef, BddRef, BddRef), BddRef>,
+}
+
+impl Bdd {
+    // variables ordered by their mapping id
+    pub fn new(num_variables: usize) -> Self {
+        Self::with_order(&(0..num_variables).map(Addr::new).collect::<Vec<Addr>>()).unwrap()
+    }
+
+    // order[level] is the id of the variable tested at that level
+    pub fn with_order(order: &[Addr]) -> Result<Self, &'static str> {
+        let mut levels = vec![usize::MAX; order.len()];
+        for (level, id) in order.iter().enumerate() {
+            if id.is_none() || id.addr() >= order.len() || levels[id.addr()] != usize::MAX {
+                return Err("The order is not a permutation of the variables");
+            }
+            levels[id.addr()] = level;
+        }
+
+        let terminal = BddNode {
+            level: order.len(),
+            low: BddRef::FALSE,
+            high: BddRef::FALSE,
+        };
+        Ok(Bdd {
+            order: order.to_vec(),
+            levels,
+            nodes: vec![terminal, terminal],
+            unique: Default::default(),
+            computed: Default::default(),
+        })
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn order(&self) -> &[Addr] {
+        &self.order
+    }
+
+    pub fn node(&self, f: BddRef) -> BddNode {
+        self.nodes[f.index()]
+    }
+
+    // variable tested by a non-terminal node
+    pub fn var(&self, f: BddRef) -> Addr {
+        self.order[self.nodes[f.index()].level]
+    }
+
+    fn make(&mut self, level: usize, low: BddRef, high: BddRef) -> BddRef {
+        if low == high {
+            return low;
+        }
+        if let Some(&f) = self.unique.get(&(level, low, high)) {
+            return f;
+        }
+        let f = BddRef(self.nodes.len() as u32);
+        self.nodes.push(BddNode { level, low, high });
+        self.unique.insert((level, low, high), f);
+        f
+    }
+
+    pub fn literal(&mut self, id: Addr, neg: bool) -> BddRef {
+        let level = self.levels[id.addr()];
+        if neg {
+            self.make(level, BddRef::TRUE, BddRef::FALSE)
+        } else {
+            self.make(level, BddRef::FALSE, BddRef::TRUE)
+        }
+    }
+
+    fn cofactors(&self, f: BddRef, level: usize) -> (BddRef, BddRef) {
+        let node = self.nodes[f.index()];
+        if node.level == level {
+            (node.low, node.high)
+        } else {
+            (f, f)
+        }
+    }
+
+    // if f then g else h
+    pub fn ite(&mut self, f: BddRef, g: BddRef, h: BddRef) -> BddRef {
+        if f == BddRef::TRUE || g == h {
+            return g;
+        }
+        if f == BddRef::FALSE {
+            return h;
+        }
+        if g == BddRef::TRUE && h == BddRef::FALSE {
+            return f;
+        }
+        if let Some(&result) = self.computed.get(&(f, g, h)) {
+            return result;
+        }
+
+        let level = [f, g, h]
+            .iter()
+            .map(|x| self.nodes[x.index()].level)
+            .min()
+            .unwrap();
+        let (f0, f1) = self.cofactors(f, level);
+        let (g0, g1) = self.cofactors(g, level);
+        let (h0, h1) = self.cofactors(h, level);
+        let low = self.ite(f0, g0, h0);
+        let high = self.ite(f1, g1, h1);
+        let result = self.make(level, low, high);
+
+        self.computed.insert((f, g, h), result);
+        result
+    }
+
+    pub fn not(&mut self, f: BddRef) -> BddRef {
+        self.ite(f, BddRef::FALSE, BddRef::TRUE)
+    }
+
+    pub fn and(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        self.ite(f, g, BddRef::FALSE)
+    }
+
+    pub fn or(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        self.ite(f, BddRef::TRUE, g)
+    }
+
+    pub fn xor(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        let not_g = self.not(g);
+        self.ite(f, not_g, g)
+    }
+
+    pub fn equiv(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        let not_g = self.not(g);
+        self.ite(f, g, not_g)
+    }
+
+    // f with the variable fixed to value
+    pub fn restrict(&mut self, f: BddRef, id: Addr, value: bool) -> BddRef {
+        let mut memo: HashMap<BddRef, BddRef> = Default::default();
+        self.restrict_level(f, self.levels[id.addr()], value, &mut memo)
+    }
+
+    fn restrict_level(
+        &mut self,
+        f: BddRef,
+        level: usize,
+        value: bool,
+        memo: &mut HashMap<BddRef, BddRef>,
+    ) -> BddRef {
+        let node = self.nodes[f.index()];
+        if node.level > level {
+            return f;
+        }
+        if node.level == level {
+            return if value { node.high } else { node.low };
+        }
+        if let Some(&result) = memo.get(&f) {
+            return result;
+        }
+        let low = self.restrict_level(node.low, level, value, memo);
+        let high = self.restrict_level(node.high, level, value, memo);
+        let result = self.make(node.level, low, high);
+        memo.insert(f, result);
+        result
+    }
+
+    pub fn eval(&self, f: BddRef, assignment: &[bool]) -> bool {
+        let mut current = f;
+        while !current.is_constant() {
+            let node = self.nodes[current.index()];
+            current = if assignment[self.order[node.level].addr()] {
+                node.high
+            } else {
+                node.low
+            };
+        }
+        current == BddRef::TRUE
+    }
+
+    // nodes reachable from f, every child before its parents
+    pub fn reachable(&self, f: BddRef) -> Vec<BddRef> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![f];
+        let mut nodes: Vec<BddRef> = Default::default();
+        while let Some(current) = stack.pop() {
+            if visited[current.index()] {
+                continue;
+            }
+            visited[current.index()] = true;
+            nodes.push(current);
+            if !current.is_constant() {
+                let node = self.nodes[current.index()];
+                stack.push(node.low);
+                stack.push(node.high);
+            }
+        }
+        // children are always created before their parents
+        nodes.sort();
+        nodes
+    }
+
+    pub fn size(&self, f: BddRef) -> usize {
+        self.reachable(f).len()
+    }
+
+    // models over all the variables of the manager
+    pub fn count(&self, f: BddRef) -> BigUint {
+        let mut counts: HashMap<BddRef, BigUint> = Default::default();
+        counts.insert(BddRef::FALSE, BigUint::default());
+        counts.insert(BddRef::TRUE, BigUint::from(1u64));
+        let nodes = self.reachable(f);
+        for &current in nodes.iter().filter(|x| !x.is_constant()) {
+            let node = self.nodes[current.index()];
+            let low = &counts[&node.low] << (self.nodes[node.low.index()].level - node.level - 1);
+            let high =
+                &counts[&node.high] << (self.nodes[node.high.index()].level - node.level - 1);
+            counts.insert(current, &low + &high);
+        }
+        &counts[&f] << self.nodes[f.index()].level
+    }
+}
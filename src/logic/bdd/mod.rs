@@ -0,0 +1,8 @@
+pub mod convert;
+pub mod manager;
+
+#[cfg(test)]
+mod tests;
+
+pub use convert::*;
+pub use manager::*;
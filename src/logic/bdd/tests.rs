@@ -0,0 +1,112 @@
+use crate::{
+    bignum::BigUint,
+    logic::{
+        eval_semiring,
+        propositional::{PMut, PropositionalTree},
+        Eval,
+    },
+    solver::naive,
+    tree::{Addr, Mapping},
+};
+
+use super::*;
+
+#[test]
+fn apply() {
+    let mut bdd = Bdd::new(3);
+    let a = bdd.literal(Addr::new(0), false);
+    let b = bdd.literal(Addr::new(1), false);
+    let not_a = bdd.not(a);
+    let not_b = bdd.not(b);
+
+    // De Morgan gives the same node
+    let left = bdd.or(a, b);
+    let right = bdd.and(not_a, not_b);
+    let right = bdd.not(right);
+    assert_eq!(left, right);
+
+    assert_eq!(bdd.xor(a, a), BddRef::FALSE);
+    assert_eq!(bdd.equiv(a, a), BddRef::TRUE);
+    assert_eq!(bdd.or(a, not_a), BddRef::TRUE);
+    assert_eq!(bdd.restrict(left, Addr::new(0), false), b);
+    assert_eq!(bdd.restrict(left, Addr::new(0), true), BddRef::TRUE);
+
+    assert_eq!(bdd.count(left), BigUint::from(6u64));
+    assert_eq!(bdd.count(BddRef::TRUE), BigUint::from(8u64));
+    assert_eq!(bdd.count(BddRef::FALSE), BigUint::default());
+    assert!(Bdd::with_order(&[Addr::new(0), Addr::new(0)]).is_err());
+}
+
+#[test]
+fn propositional() {
+    let tree = PropositionalTree::build(|builder| {
+        builder.or(
+            |left| left.and(|left| left.var("A"), |right| right.var("B")),
+            |right| {
+                right.and(
+                    |left| left.not(|inner| inner.var("A")),
+                    |right| right.or(|left| left.var("C"), |right| right.var("D")),
+                )
+            },
+        )
+    });
+
+    for order in [[0, 1, 2, 3], [3, 2, 1, 0], [2, 0, 3, 1]] {
+        let order: Vec<Addr> = order.into_iter().map(Addr::new).collect();
+        let mut bdd = Bdd::with_order(&order).unwrap();
+        let f = propositional_to_bdd(&mut bdd, &tree);
+        assert_eq!(bdd.count(f), naive::count(&tree));
+
+        let pc = bdd_to_circuit(&bdd, f, &tree).unwrap();
+        assert_eq!(pc.get_id(&"C".to_string()), tree.get_id(&"C".to_string()));
+        assert_eq!(
+            eval_semiring(&pc, |_, _| BigUint::from(1u64)),
+            naive::count(&tree)
+        );
+        for x in 0..16 {
+            let assignment: Vec<bool> = (0..4).map(|i| x & (1 << i) != 0).collect();
+            assert_eq!(bdd.eval(f, &assignment), tree.eval(&assignment));
+            assert_eq!(pc.eval(&assignment), tree.eval(&assignment) as u8 as f32);
+        }
+    }
+
+    let mut bdd = Bdd::new(2);
+    let empty = PropositionalTree::default();
+    assert_eq!(
+        bdd_to_circuit(&bdd, BddRef::FALSE, &empty),
+        Err("Formula is unsatisfiable")
+    );
+    let a = bdd.literal(Addr::new(1), true);
+    let pc = bdd_to_circuit(&bdd, a, &empty).unwrap();
+    assert_eq!(pc.num_named(), 2);
+    assert_eq!(pc.eval(&vec![None, None]), 2.0);
+}
+
+#[test]
+fn ordering() {
+    // (x1∧y1)∨(x2∧y2)∨(x3∧y3)∨(x4∧y4) is linear when pairs are adjacent
+    let mut tree: PropositionalTree = Default::default();
+    for _ in 0..8 {
+        tree.add_anon();
+    }
+    tree.builder(|builder| {
+        builder.disjunction(&mut (0..4), |builder, i| {
+            builder.and(
+                |left| left.var(Addr::new(i)),
+                |right| right.var(Addr::new(i + 4)),
+            )
+        })
+    });
+
+    let interleaved: Vec<Addr> = (0..4)
+        .flat_map(|i| [Addr::new(i), Addr::new(i + 4)])
+        .collect();
+    let mut good = Bdd::with_order(&interleaved).unwrap();
+    let mut bad = Bdd::new(8);
+    let f_good = propositional_to_bdd(&mut good, &tree);
+    let f_bad = propositional_to_bdd(&mut bad, &tree);
+
+    assert_eq!(good.count(f_good), bad.count(f_bad));
+    assert_eq!(good.size(f_good), 10);
+    assert!(bad.size(f_bad) > good.size(f_good));
+}
@@ -0,0 +1,147 @@
+use crate::tree::{Addr, LinkingNode, Mapping};
+
+use super::{check_boolean, log_eval_nodes, log_sum_exp, CircuitTree, PCicruit, Weight};
+
+// number of samples evaluated together, keeps the node arrays in cache
+const CHUNK_SIZE: usize = 256;
+
+// column-major set of complete assignments, columns[variable][sample]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Batch {
+    pub num_samples: usize,
+    pub columns: Vec<Vec<bool>>,
+}
+
+impl Batch {
+    pub fn from_rows(num_variables: usize, rows: &[Vec<bool>]) -> Self {
+        Batch {
+            num_samples: rows.len(),
+            columns: (0..num_variables)
+                .map(|var| rows.iter().map(|row| row[var]).collect())
+                .collect(),
+        }
+    }
+
+    pub fn row(&self, sample: usize) -> Vec<bool> {
+        self.columns.iter().map(|column| column[sample]).collect()
+    }
+}
+
+// natural logarithm of the circuit value for every sample of the batch, the
+// leaves have to be boolean
+pub fn log_eval_batch<W: Weight>(
+    circuit: &CircuitTree<W>,
+    batch: &Batch,
+) -> Result<Vec<f64>, &'static str> {
+    check_boolean(circuit)?;
+    let mut result = vec![f64::NEG_INFINITY; batch.num_samples];
+    log_eval_range(circuit, &circuit.topological(), batch, 0, &mut result);
+    Ok(result)
+}
+
+// evaluates the samples from begin onwards into result
+pub(super) fn log_eval_range<W: Weight>(
+    circuit: &CircuitTree<W>,
+    order: &[Addr],
+    batch: &Batch,
+    begin: usize,
+    result: &mut [f64],
+) {
+    let output = circuit.output().idx;
+    if output.is_none() {
+        return;
+    }
+
+    let mut values: Vec<Vec<f64>> = vec![Vec::new(); circuit.num_nodes()];
+    for offset in (0..result.len()).step_by(CHUNK_SIZE) {
+        let end = (offset + CHUNK_SIZE).min(result.len());
+        for &idx in order.iter() {
+            let mut current = std::mem::take(&mut values[idx.addr()]);
+            current.clear();
+            eval_chunk(
+                circuit,
+                idx,
+                batch,
+                begin + offset..begin + end,
+                &values,
+                &mut current,
+            );
+            values[idx.addr()] = current;
+        }
+        result[offset..end].copy_from_slice(&values[output.addr()]);
+    }
+}
+
+#[inline]
+fn eval_chunk<W: Weight>(
+    circuit: &CircuitTree<W>,
+    idx: Addr,
+    batch: &Batch,
+    range: std::ops::Range<usize>,
+    values: &[Vec<f64>],
+    current: &mut Vec<f64>,
+) {
+    let operands = circuit[idx].node.operands();
+    match circuit[idx].value {
+        PCicruit::Variable { id, neg } => {
+            current.extend(batch.columns[id.addr()][range].iter().map(|&x| {
+                if x == neg {
+                    f64::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            }))
+        }
+        leaf @ PCicruit::Bernoulli { id, .. } => {
+            let table = [leaf.log_density(Some(0.0)), leaf.log_density(Some(1.0))];
+            current.extend(
+                batch.columns[id.addr()][range]
+                    .iter()
+                    .map(|&x| table[x as usize]),
+            )
+        }
+        PCicruit::Indicator { .. } | PCicruit::Gaussian { .. } => {
+            unreachable!("Checked by the batch entry points")
+        }
+        PCicruit::Product => current.extend(
+            values[operands[0].addr()]
+                .iter()
+                .zip(values[operands[1].addr()].iter())
+                .map(|(left, right)| left + right),
+        ),
+        PCicruit::Sum { left, right } => {
+            let (w_left, w_right) = (left.log_prob(), right.log_prob());
+            current.extend(
+                values[operands[0].addr()]
+                    .iter()
+                    .zip(values[operands[1].addr()].iter())
+                    .map(|(left, right)| log_sum_exp(w_left + left, w_right + right)),
+            )
+        }
+    }
+}
+
+// circuit value of every sample of the batch
+pub fn eval_batch<W: Weight>(
+    circuit: &CircuitTree<W>,
+    batch: &Batch,
+) -> Result<Vec<f64>, &'static str> {
+    Ok(log_eval_batch(circuit, batch)?
+        .into_iter()
+        .map(f64::exp)
+        .collect())
+}
+
+// total log-likelihood of the batch, normalised by the partition function of the circuit
+pub fn log_likelihood<W: Weight>(
+    circuit: &CircuitTree<W>,
+    batch: &Batch,
+) -> Result<f64, &'static str> {
+    let values = log_eval_batch(circuit, batch)?;
+    if circuit.output().idx.is_none() {
+        return Ok(f64::NEG_INFINITY);
+    }
+    let partition =
+        log_eval_nodes(circuit, &vec![None; circuit.num_named()])[circuit.output().idx.addr()];
+    Ok(values.iter().map(|value| value - partition).sum())
+}
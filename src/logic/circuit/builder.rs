@@ -1,12 +1,16 @@
-use std::ops::IndexMut;
+use crate::tree::{Addr, IndexedMutRef, IntoAddr, Node, NodeAllocator};
 
-use crate::tree::{Addr, IndexedMutRef, IntoAddr, Node, NodeAllocator, NodeValue};
-
-use super::PCicruit;
+use super::{PCicruit, Weight};
 
 pub trait PCMut: Sized {
+    type Weight: Weight;
+
     fn var<T: IntoAddr<Self, Addr>>(&mut self, id: T) -> Addr;
     fn not_var<T: IntoAddr<Self, Addr>>(&mut self, id: T) -> Addr;
+    fn bernoulli<T: IntoAddr<Self, Addr>>(&mut self, id: T, p: Self::Weight) -> Addr;
+    fn indicator<T: IntoAddr<Self, Addr>>(&mut self, id: T, value: u32) -> Addr;
+    fn gaussian<T: IntoAddr<Self, Addr>>(&mut self, id: T, mean: f64, variance: f64) -> Addr;
+    fn categorical<T: IntoAddr<Self, Addr>>(&mut self, id: T, probs: &[f64]) -> Addr;
     fn prod<F: Fn(&mut Self) -> Addr, G: Fn(&mut Self) -> Addr>(
         &mut self,
         left: F,
@@ -19,9 +23,9 @@ pub trait PCMut: Sized {
     ) -> Addr;
     fn sum_w<F: Fn(&mut Self) -> Addr, G: Fn(&mut Self) -> Addr>(
         &mut self,
-        weight_left: f32,
+        weight_left: Self::Weight,
         left: F,
-        weight_right: f32,
+        weight_right: Self::Weight,
         right: G,
     ) -> Addr;
     fn prod_n<F: Fn(&mut Self, T::Item) -> Addr, T: Iterator>(
@@ -29,18 +33,20 @@ pub trait PCMut: Sized {
         iter: &mut T,
         inner: F,
     ) -> Addr;
-    fn sum_n<F: Fn(&mut Self, T::Item) -> (Addr, f32), T: Iterator>(
+    fn sum_n<F: Fn(&mut Self, T::Item) -> (Addr, Self::Weight), T: Iterator>(
         &mut self,
         iter: &mut T,
         inner: F,
     ) -> Addr;
 }
 
-impl<'a, T> PCMut for IndexedMutRef<'a, T>
+impl<'a, T, W> PCMut for IndexedMutRef<'a, T>
 where
-    T: IndexMut<Addr, Output = NodeValue<Node<2>, PCicruit>>
-        + NodeAllocator<Value = PCicruit, Node = Node<2>>,
+    T: NodeAllocator<Value = PCicruit<W>, Node = Node<2>>,
+    W: Weight,
 {
+    type Weight = W;
+
     #[inline(always)]
     fn var<U: IntoAddr<Self, Addr>>(&mut self, id: U) -> Addr {
         let addr = id.get_addr(self);
@@ -65,6 +71,53 @@ where
         )
     }
 
+    #[inline(always)]
+    fn bernoulli<U: IntoAddr<Self, Addr>>(&mut self, id: U, p: W) -> Addr {
+        let addr = id.get_addr(self);
+        self.array.push(PCicruit::Bernoulli { id: addr, p }, &[])
+    }
+
+    #[inline(always)]
+    fn indicator<U: IntoAddr<Self, Addr>>(&mut self, id: U, value: u32) -> Addr {
+        let addr = id.get_addr(self);
+        self.array
+            .push(PCicruit::Indicator { id: addr, value }, &[])
+    }
+
+    #[inline(always)]
+    fn gaussian<U: IntoAddr<Self, Addr>>(&mut self, id: U, mean: f64, variance: f64) -> Addr {
+        let addr = id.get_addr(self);
+        self.array.push(
+            PCicruit::Gaussian {
+                id: addr,
+                mean,
+                variance,
+            },
+            &[],
+        )
+    }
+
+    // weighted sum of the indicators of every value, the probabilities are normalised
+    // and need a positive total
+    fn categorical<U: IntoAddr<Self, Addr>>(&mut self, id: U, probs: &[f64]) -> Addr {
+        assert!(!probs.is_empty(), "Categorical leaf without values");
+        let total: f64 = probs.iter().sum();
+        assert!(
+            total > 0.0,
+            "Categorical probabilities must have a positive total"
+        );
+        let addr = id.get_addr(self);
+        if probs.len() == 1 {
+            return self.indicator(addr, 0);
+        }
+        self.sum_n(&mut probs.iter().enumerate(), |builder, (value, &p)| {
+            (
+                builder.indicator(addr, value as u32),
+                W::from_prob(p / total),
+            )
+        })
+    }
+
     #[inline(always)]
     fn prod<F: Fn(&mut Self) -> Addr, G: Fn(&mut Self) -> Addr>(
         &mut self,
@@ -86,8 +139,8 @@ where
         let right_id = right(self);
         self.array.push(
             PCicruit::Sum {
-                left: 1.0,
-                right: 1.0,
+                left: W::ONE,
+                right: W::ONE,
             },
             &[left_id, right_id],
         )
@@ -96,9 +149,9 @@ where
     #[inline(always)]
     fn sum_w<F: Fn(&mut Self) -> Addr, G: Fn(&mut Self) -> Addr>(
         &mut self,
-        weight_left: f32,
+        weight_left: W,
         left: F,
-        weight_right: f32,
+        weight_right: W,
         right: G,
     ) -> Addr {
         let left_id = left(self);
@@ -130,7 +183,7 @@ where
         current_id
     }
 
-    fn sum_n<F: Fn(&mut Self, U::Item) -> (Addr, f32), U: Iterator>(
+    fn sum_n<F: Fn(&mut Self, U::Item) -> (Addr, W), U: Iterator>(
         &mut self,
         iter: &mut U,
         inner: F,
@@ -150,7 +203,7 @@ where
                 },
                 &[current_id, inner_id],
             );
-            current_w = 1.0;
+            current_w = W::ONE;
         }
         current_id
     }
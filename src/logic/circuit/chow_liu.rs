@@ -0,0 +1,282 @@
+use std::iter::once;
+
+use crate::{
+    random::Rng,
+    tree::{Addr, IndexedMutRef, Mapping},
+};
+
+use super::{iterate, mutual_information, Fit, PCMut, ProbabilisticCircuitTree};
+
+// Bayesian network over booleans where every variable has at most one parent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChowLiuTree {
+    // None for the root
+    pub parents: Vec<Option<usize>>,
+    // P(X = true) given the parent false and true, both the same at the root
+    pub probs: Vec<[f64; 2]>,
+}
+
+fn num_variables(data: &[Vec<bool>]) -> Result<usize, &'static str> {
+    let Some(first) = data.first() else {
+        return Err("No samples");
+    };
+    if data.iter().any(|sample| sample.len() != first.len()) {
+        return Err("Samples have different lengths");
+    }
+    Ok(first.len())
+}
+
+// joint counts indexed by the value of a then the value of b, only the
+// pairs with a <= b are stored
+fn joint(pairs: &[Vec<[[f64; 2]; 2]>], a: usize, b: usize) -> [[f64; 2]; 2] {
+    if a <= b {
+        pairs[a][b]
+    } else {
+        let c = pairs[b][a];
+        [[c[0][0], c[1][0]], [c[0][1], c[1][1]]]
+    }
+}
+
+fn smoothed(ones: f64, total: f64, smoothing: f64) -> f64 {
+    if total + 2.0 * smoothing > 0.0 {
+        (ones + smoothing) / (total + 2.0 * smoothing)
+    } else {
+        0.5
+    }
+}
+
+impl ChowLiuTree {
+    pub fn fit(data: &[Vec<bool>], smoothing: f64) -> Result<Self, &'static str> {
+        num_variables(data)?;
+        Ok(Self::fit_weighted(data, &vec![1.0; data.len()], smoothing))
+    }
+
+    // maximum spanning tree of the pairwise mutual information of the
+    // weighted samples, rooted at the first variable
+    pub fn fit_weighted(data: &[Vec<bool>], weights: &[f64], smoothing: f64) -> Self {
+        let n = data.first().map_or(0, |sample| sample.len());
+        let mut pairs = vec![vec![[[0.0f64; 2]; 2]; n]; n];
+        for (sample, &weight) in data.iter().zip(weights.iter()) {
+            for a in 0..n {
+                for b in a..n {
+                    pairs[a][b][sample[a] as usize][sample[b] as usize] += weight;
+                }
+            }
+        }
+
+        // Prim's algorithm, best holds the heaviest edge to the tree
+        let mut parents: Vec<Option<usize>> = vec![None; n];
+        let mut in_tree = vec![false; n];
+        let mut best: Vec<(f64, Option<usize>)> = vec![(f64::NEG_INFINITY, None); n];
+        if n > 0 {
+            best[0].0 = 0.0;
+        }
+        for _ in 0..n {
+            let v = (0..n)
+                .filter(|&v| !in_tree[v])
+                .max_by(|&a, &b| best[a].0.total_cmp(&best[b].0))
+                .unwrap();
+            in_tree[v] = true;
+            parents[v] = best[v].1;
+            for u in (0..n).filter(|&u| !in_tree[u]) {
+                let information = mutual_information(&joint(&pairs, u, v));
+                if information > best[u].0 {
+                    best[u] = (information, Some(v));
+                }
+            }
+        }
+
+        let probs = (0..n)
+            .map(|v| match parents[v] {
+                None => {
+                    let c = pairs[v][v];
+                    let p = smoothed(c[1][1], c[0][0] + c[1][1], smoothing);
+                    [p, p]
+                }
+                Some(u) => {
+                    let c = joint(&pairs, u, v);
+                    [0, 1].map(|x| smoothed(c[x][1], c[x][0] + c[x][1], smoothing))
+                }
+            })
+            .collect();
+        ChowLiuTree { parents, probs }
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn log_prob(&self, sample: &[bool]) -> f64 {
+        (0..self.num_variables())
+            .map(|v| {
+                let p = self.probs[v][self.parents[v].map_or(0, |u| sample[u] as usize)];
+                if sample[v] {
+                    p.ln()
+                } else {
+                    (1.0 - p).ln()
+                }
+            })
+            .sum()
+    }
+
+    // smooth, decomposable and deterministic
+    pub fn to_circuit(&self) -> ProbabilisticCircuitTree {
+        let mut tree: ProbabilisticCircuitTree = Default::default();
+        for _ in 0..self.num_variables() {
+            tree.add_anon();
+        }
+        tree.builder(|builder| self.emit(builder));
+        tree
+    }
+
+    // a sum over the values of every variable weighted by its probability
+    // given the parent, the branches are shared by both values of the parent
+    fn emit(&self, builder: &mut IndexedMutRef<ProbabilisticCircuitTree>) -> Addr {
+        let n = self.num_variables();
+        let mut children: Vec<Vec<usize>> = vec![Default::default(); n];
+        let mut order: Vec<usize> = Default::default();
+        for v in 0..n {
+            match self.parents[v] {
+                Some(u) => children[u].push(v),
+                None => order.push(v),
+            }
+        }
+        let roots = order.clone();
+        let mut i = 0;
+        while i < order.len() {
+            order.extend(children[order[i]].iter().copied());
+            i += 1;
+        }
+
+        let mut conditionals = vec![[Addr::NONE; 2]; n];
+        for &v in order.iter().rev() {
+            let id = Addr::new(v);
+            let branches: Vec<Addr> = (0..2)
+                .map(|x| {
+                    let lit = if x == 1 {
+                        builder.var(id)
+                    } else {
+                        builder.not_var(id)
+                    };
+                    let operands = children[v].iter().map(|&c| conditionals[c][x]);
+                    builder.prod_n(&mut once(lit).chain(operands), |_, addr| addr)
+                })
+                .collect();
+            let values = if self.parents[v].is_some() { 2 } else { 1 };
+            for (conditional, &p) in conditionals[v]
+                .iter_mut()
+                .zip(self.probs[v].iter())
+                .take(values)
+            {
+                *conditional =
+                    builder.sum_w((1.0 - p) as f32, |_| branches[0], p as f32, |_| branches[1]);
+            }
+        }
+        builder.prod_n(&mut roots.iter(), |_, &v| conditionals[v][0])
+    }
+}
+
+// weighted sum of Chow-Liu trees, each learning its own structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixtureOfTrees {
+    pub weights: Vec<f64>,
+    pub trees: Vec<ChowLiuTree>,
+}
+
+impl MixtureOfTrees {
+    // k trees fitted on a random soft partition of the samples
+    pub fn new(
+        data: &[Vec<bool>],
+        k: usize,
+        smoothing: f64,
+        rng: &mut Rng,
+    ) -> Result<Self, &'static str> {
+        num_variables(data)?;
+        if k == 0 {
+            return Err("At least one tree is needed");
+        }
+        let responsibilities: Vec<Vec<f64>> = data
+            .iter()
+            .map(|_| {
+                let r: Vec<f64> = (0..k).map(|_| 0.5 + rng.next_f64()).collect();
+                let total: f64 = r.iter().sum();
+                r.iter().map(|x| x / total).collect()
+            })
+            .collect();
+        Ok(Self::maximise(data, &responsibilities, k, smoothing))
+    }
+
+    fn maximise(
+        data: &[Vec<bool>],
+        responsibilities: &[Vec<f64>],
+        k: usize,
+        smoothing: f64,
+    ) -> Self {
+        let mut weights = Vec::with_capacity(k);
+        let mut trees = Vec::with_capacity(k);
+        for c in 0..k {
+            let column: Vec<f64> = responsibilities.iter().map(|r| r[c]).collect();
+            let total: f64 = column.iter().sum();
+            weights.push((total + smoothing) / (data.len() as f64 + k as f64 * smoothing));
+            trees.push(ChowLiuTree::fit_weighted(data, &column, smoothing));
+        }
+        MixtureOfTrees { weights, trees }
+    }
+
+    // log probability of every tree weighted by its mixture weight
+    fn log_joint(&self, sample: &[bool]) -> Vec<f64> {
+        self.weights
+            .iter()
+            .zip(self.trees.iter())
+            .map(|(w, tree)| w.ln() + tree.log_prob(sample))
+            .collect()
+    }
+
+    pub fn log_prob(&self, sample: &[bool]) -> f64 {
+        let logs = self.log_joint(sample);
+        let max = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        max + logs.iter().map(|x| (x - max).exp()).sum::<f64>().ln()
+    }
+
+    // one expectation-maximisation step, returns the log-likelihood before the update
+    pub fn em_step(&mut self, data: &[Vec<bool>], smoothing: f64) -> Result<f64, &'static str> {
+        if num_variables(data)? != self.trees[0].num_variables() {
+            return Err("Samples do not match the variables of the trees");
+        }
+        let mut log_likelihood = 0.0;
+        let responsibilities: Vec<Vec<f64>> = data
+            .iter()
+            .map(|sample| {
+                let logs = self.log_joint(sample);
+                let total = self.log_prob(sample);
+                log_likelihood += total;
+                logs.iter().map(|x| (x - total).exp()).collect()
+            })
+            .collect();
+        *self = Self::maximise(data, &responsibilities, self.trees.len(), smoothing);
+        Ok(log_likelihood)
+    }
+
+    // expectation-maximisation, returns the log-likelihood of every iteration
+    pub fn fit(&mut self, data: &[Vec<bool>], fit: &Fit) -> Result<Vec<f64>, &'static str> {
+        iterate(fit, || self.em_step(data, fit.smoothing))
+    }
+
+    // smooth and decomposable, a sum over the circuits of the trees
+    pub fn to_circuit(&self) -> ProbabilisticCircuitTree {
+        let mut tree: ProbabilisticCircuitTree = Default::default();
+        for _ in 0..self.trees[0].num_variables() {
+            tree.add_anon();
+        }
+        tree.builder(|builder| {
+            if builder.array.num_named() == 0 {
+                return Addr::NONE;
+            }
+            builder.sum_n(
+                &mut self.weights.iter().zip(self.trees.iter()),
+                |builder, (&w, tree)| (tree.emit(builder), w as f32),
+            )
+        });
+        tree
+    }
+}
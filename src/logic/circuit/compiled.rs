@@ -0,0 +1,202 @@
+use crate::tree::{Addr, LinkingNode, Mapping};
+
+use super::{check_boolean, log_sum_exp, CircuitTree, Gradient, PCicruit, Weight};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Literal,
+    // parameterised leaf, its weights are the values for false and true
+    Leaf,
+    Product,
+    Sum,
+}
+
+// flat structure-of-arrays layout, nodes are stored in topological order and the output is last
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledCircuit {
+    pub num_variables: usize,
+    pub kinds: Vec<NodeKind>,
+    // operand positions, for literals the variable id and 1 if negated
+    pub children: Vec<[u32; 2]>,
+    // sum weights and their logarithm, unused for the other nodes
+    pub weights: Vec<[f64; 2]>,
+    pub log_weights: Vec<[f64; 2]>,
+    // address of every node in the source tree
+    pub addrs: Vec<Addr>,
+}
+
+impl CompiledCircuit {
+    // the leaves have to be boolean, mixed data goes through log_eval_mixed
+    pub fn new<W: Weight>(circuit: &CircuitTree<W>) -> Result<Self, &'static str> {
+        check_boolean(circuit)?;
+        let order = circuit.topological();
+        let mut position = vec![u32::MAX; circuit.num_nodes()];
+        for (pos, idx) in order.iter().enumerate() {
+            position[idx.addr()] = pos as u32;
+        }
+
+        let mut compiled = CompiledCircuit {
+            num_variables: circuit.num_named(),
+            kinds: Vec::with_capacity(order.len()),
+            children: Vec::with_capacity(order.len()),
+            weights: Vec::with_capacity(order.len()),
+            log_weights: Vec::with_capacity(order.len()),
+            addrs: order.clone(),
+        };
+        for idx in order {
+            let operands = circuit[idx].node.operands();
+            let (kind, children, log_weights) = match circuit[idx].value {
+                PCicruit::Variable { id, neg } => {
+                    (NodeKind::Literal, [id.addr() as u32, neg as u32], [0.0; 2])
+                }
+                leaf @ PCicruit::Bernoulli { id, .. } => (
+                    NodeKind::Leaf,
+                    [id.addr() as u32, 0],
+                    [leaf.log_density(Some(0.0)), leaf.log_density(Some(1.0))],
+                ),
+                PCicruit::Indicator { .. } | PCicruit::Gaussian { .. } => {
+                    unreachable!("Checked above")
+                }
+                PCicruit::Product => (
+                    NodeKind::Product,
+                    [position[operands[0].addr()], position[operands[1].addr()]],
+                    [0.0; 2],
+                ),
+                PCicruit::Sum { left, right } => (
+                    NodeKind::Sum,
+                    [position[operands[0].addr()], position[operands[1].addr()]],
+                    [left.log_prob(), right.log_prob()],
+                ),
+            };
+            compiled.kinds.push(kind);
+            compiled.children.push(children);
+            compiled.weights.push(log_weights.map(f64::exp));
+            compiled.log_weights.push(log_weights);
+        }
+        Ok(compiled)
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.kinds.len()
+    }
+
+    // fills values with the value of every node and returns the output value
+    pub fn evaluate(&self, evidence: &[Option<bool>], values: &mut Vec<f64>) -> f64 {
+        values.clear();
+        for pos in 0..self.kinds.len() {
+            let [left, right] = self.children[pos];
+            let value = match self.kinds[pos] {
+                NodeKind::Literal => match evidence[left as usize] {
+                    Some(value) if value == (right != 0) => 0.0,
+                    _ => 1.0,
+                },
+                NodeKind::Leaf => match evidence[left as usize] {
+                    Some(value) => self.weights[pos][value as usize],
+                    None => 1.0,
+                },
+                NodeKind::Product => values[left as usize] * values[right as usize],
+                NodeKind::Sum => {
+                    let [w_left, w_right] = self.weights[pos];
+                    w_left * values[left as usize] + w_right * values[right as usize]
+                }
+            };
+            values.push(value);
+        }
+        values.last().copied().unwrap_or(0.0)
+    }
+
+    // same as evaluate in log-space
+    pub fn log_evaluate(&self, evidence: &[Option<bool>], values: &mut Vec<f64>) -> f64 {
+        values.clear();
+        for pos in 0..self.kinds.len() {
+            let value = self.log_node(pos, evidence, |child| values[child]);
+            values.push(value);
+        }
+        values.last().copied().unwrap_or(f64::NEG_INFINITY)
+    }
+
+    #[inline]
+    pub(super) fn log_node<F: Fn(usize) -> f64>(
+        &self,
+        pos: usize,
+        evidence: &[Option<bool>],
+        value: F,
+    ) -> f64 {
+        let [left, right] = self.children[pos];
+        match self.kinds[pos] {
+            NodeKind::Literal => match evidence[left as usize] {
+                Some(value) if value == (right != 0) => f64::NEG_INFINITY,
+                _ => 0.0,
+            },
+            NodeKind::Leaf => match evidence[left as usize] {
+                Some(value) => self.log_weights[pos][value as usize],
+                None => 0.0,
+            },
+            NodeKind::Product => value(left as usize) + value(right as usize),
+            NodeKind::Sum => {
+                let [w_left, w_right] = self.log_weights[pos];
+                log_sum_exp(
+                    w_left + value(left as usize),
+                    w_right + value(right as usize),
+                )
+            }
+        }
+    }
+
+    // positions grouped by depth, nodes of a layer only depend on previous layers
+    pub fn layers(&self) -> Vec<Vec<u32>> {
+        let mut depth = vec![0usize; self.kinds.len()];
+        let mut layers: Vec<Vec<u32>> = Default::default();
+        for pos in 0..self.kinds.len() {
+            if !matches!(self.kinds[pos], NodeKind::Literal | NodeKind::Leaf) {
+                let [left, right] = self.children[pos];
+                depth[pos] = 1 + depth[left as usize].max(depth[right as usize]);
+            }
+            if layers.len() <= depth[pos] {
+                layers.resize(depth[pos] + 1, Vec::new());
+            }
+            layers[depth[pos]].push(pos as u32);
+        }
+        layers
+    }
+
+    // derivatives from the node values of evaluate, indexed by node position
+    pub fn gradient(&self, values: &[f64]) -> Gradient {
+        let mut gradient = Gradient {
+            nodes: vec![0.0; self.kinds.len()],
+            weights: vec![[0.0; 2]; self.kinds.len()],
+        };
+        if let Some(output) = gradient.nodes.last_mut() {
+            *output = 1.0;
+        }
+        for pos in (0..self.kinds.len()).rev() {
+            let derivative = gradient.nodes[pos];
+            let [left, right] = self.children[pos];
+            match self.kinds[pos] {
+                NodeKind::Literal | NodeKind::Leaf => {}
+                NodeKind::Product => {
+                    gradient.nodes[left as usize] += derivative * values[right as usize];
+                    gradient.nodes[right as usize] += derivative * values[left as usize];
+                }
+                NodeKind::Sum => {
+                    let [w_left, w_right] = self.weights[pos];
+                    gradient.nodes[left as usize] += derivative * w_left;
+                    gradient.nodes[right as usize] += derivative * w_right;
+                    gradient.weights[pos] = [
+                        derivative * values[left as usize],
+                        derivative * values[right as usize],
+                    ];
+                }
+            }
+        }
+        gradient
+    }
+}
+
+impl<W: Weight> TryFrom<&CircuitTree<W>> for CompiledCircuit {
+    type Error = &'static str;
+
+    fn try_from(circuit: &CircuitTree<W>) -> Result<Self, Self::Error> {
+        CompiledCircuit::new(circuit)
+    }
+}
@@ -0,0 +1,109 @@
+use std::{cell::Cell, collections::HashMap};
+
+use crate::{
+    logic::propositional::{Cnf, ComponentSearch, Decomposition, Literal, PropositionalTree},
+    tree::{Addr, IndexedMutRef, Mapping},
+};
+
+use super::{PCMut, ProbabilisticCircuitTree};
+
+struct Compiler<'a, 'b> {
+    builder: &'a mut IndexedMutRef<'b, ProbabilisticCircuitTree>,
+    literals: HashMap<Literal, Addr>,
+    free: HashMap<usize, Addr>,
+}
+
+impl<'a, 'b> Decomposition for Compiler<'a, 'b> {
+    type Value = Addr;
+
+    fn literal(&mut self, lit: Literal) -> Addr {
+        if let Some(&addr) = self.literals.get(&lit) {
+            return addr;
+        }
+        let addr = if lit.is_neg() {
+            self.builder.not_var(lit.id())
+        } else {
+            self.builder.var(lit.id())
+        };
+        self.literals.insert(lit, addr);
+        addr
+    }
+
+    // keeps the circuit smooth over variables left unconstrained
+    fn free(&mut self, var: usize) -> Addr {
+        if let Some(&addr) = self.free.get(&var) {
+            return addr;
+        }
+        let pos = self.literal(Literal::new(Addr::new(var), false));
+        let neg = self.literal(Literal::new(Addr::new(var), true));
+        let addr = self.builder.sum(|_| pos, |_| neg);
+        self.free.insert(var, addr);
+        addr
+    }
+
+    fn product(&mut self, mut parts: Vec<Addr>) -> Addr {
+        while parts.len() > 1 {
+            parts = parts
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [left, right] => self.builder.prod(|_| left, |_| right),
+                    _ => pair[0],
+                })
+                .collect();
+        }
+        parts[0]
+    }
+
+    fn decision(&mut self, positive: Addr, negative: Addr) -> Addr {
+        self.builder.sum(|_| positive, |_| negative)
+    }
+}
+
+// deterministic, decomposable and smooth over all the variables, an
+// unsatisfiable formula or one without variables has no circuit
+pub fn cnf_to_ddnnf(cnf: &Cnf) -> Result<ProbabilisticCircuitTree, &'static str> {
+    let mut tree: ProbabilisticCircuitTree = Default::default();
+    for _ in 0..cnf.num_variables {
+        tree.add_anon();
+    }
+    build(&mut tree, cnf)?;
+    Ok(tree)
+}
+
+pub fn propositional_to_ddnnf(
+    tree: &PropositionalTree,
+) -> Result<ProbabilisticCircuitTree, &'static str> {
+    let cnf = Cnf::from_tree(tree)?;
+    let mut circuit: ProbabilisticCircuitTree = Default::default();
+    circuit.copy_named(tree);
+    build(&mut circuit, &cnf)?;
+    Ok(circuit)
+}
+
+// circuits have no constant node, so true needs a variable to be smoothed over
+fn build(circuit: &mut ProbabilisticCircuitTree, cnf: &Cnf) -> Result<(), &'static str> {
+    if cnf.num_variables == 0 && cnf.clauses.is_empty() {
+        return Err("Formula has no variables");
+    }
+    let satisfiable = Cell::new(true);
+    circuit.builder(|builder| {
+        compile_cnf(builder, cnf).unwrap_or_else(|| {
+            satisfiable.set(false);
+            Addr::NONE
+        })
+    });
+    match satisfiable.get() {
+        true => Ok(()),
+        false => Err("Formula is unsatisfiable"),
+    }
+}
+
+// None if unsatisfiable
+fn compile_cnf(builder: &mut IndexedMutRef<ProbabilisticCircuitTree>, cnf: &Cnf) -> Option<Addr> {
+    let compiler = Compiler {
+        builder,
+        literals: Default::default(),
+        free: Default::default(),
+    };
+    ComponentSearch::new(compiler).search(cnf)
+}
@@ -1,31 +1,196 @@
-use crate::{logic::semantic::Eval, tree::IndexedRef};
+use num_traits::{Float, One, Zero};
 
-use super::{PCRef, PCicruit, ProbabilisticCircuitTree};
+use crate::{
+    logic::{semantic::Eval, Semiring, SemiringEval},
+    tree::{Addr, IndexedRef, LinkingNode},
+};
 
-impl<'a> Eval<bool> for IndexedRef<'a, ProbabilisticCircuitTree> {
-    type Output = f32;
+use super::{log_boolean_leaf, log_leaf, log_sum_exp, CircuitTree, PCRef, PCicruit, Weight};
+
+pub trait LogEval<D> {
+    fn log_eval(&self, assignment: &[D]) -> f64;
+}
+
+impl<'a, W: Weight> Eval<bool> for IndexedRef<'a, CircuitTree<W>> {
+    type Output = W::Value;
 
     fn eval(&self, assignment: &Vec<bool>) -> Self::Output {
         match self.as_ref().value {
-            PCicruit::Variable { id, .. } => {
-                if assignment[id.addr()] {
-                    1.0
-                } else {
-                    0.0
-                }
-            }
+            PCicruit::Variable { id, neg } => indicator(Some(assignment[id.addr()]), neg),
             PCicruit::Product => self.left().eval(assignment) * self.right().eval(assignment),
             PCicruit::Sum { left, right } => {
-                (left * self.left().eval(assignment)) + (right * self.right().eval(assignment))
+                (left.value() * self.left().eval(assignment))
+                    + (right.value() * self.right().eval(assignment))
+            }
+            leaf => {
+                let value = assignment[leaf.leaf_id().unwrap().addr()];
+                from_log(log_boolean_leaf(&leaf, Some(value)))
             }
         }
     }
 }
 
-impl Eval<bool> for ProbabilisticCircuitTree {
-    type Output = f32;
+impl<W: Weight> Eval<bool> for CircuitTree<W> {
+    type Output = W::Value;
 
     fn eval(&self, assignment: &Vec<bool>) -> Self::Output {
         self.output().eval(assignment)
     }
 }
+
+// unobserved variables (None) are marginalised out
+impl<'a, W: Weight> Eval<Option<bool>> for IndexedRef<'a, CircuitTree<W>> {
+    type Output = W::Value;
+
+    fn eval(&self, assignment: &Vec<Option<bool>>) -> Self::Output {
+        match self.as_ref().value {
+            PCicruit::Variable { id, neg } => indicator(assignment[id.addr()], neg),
+            PCicruit::Product => self.left().eval(assignment) * self.right().eval(assignment),
+            PCicruit::Sum { left, right } => {
+                (left.value() * self.left().eval(assignment))
+                    + (right.value() * self.right().eval(assignment))
+            }
+            leaf => from_log(log_leaf(&leaf, assignment)),
+        }
+    }
+}
+
+impl<W: Weight> Eval<Option<bool>> for CircuitTree<W> {
+    type Output = W::Value;
+
+    fn eval(&self, assignment: &Vec<Option<bool>>) -> Self::Output {
+        self.output().eval(assignment)
+    }
+}
+
+// natural logarithm of the circuit value, sums become log-sum-exp and products additions
+impl<'a, W: Weight> LogEval<Option<bool>> for IndexedRef<'a, CircuitTree<W>> {
+    fn log_eval(&self, assignment: &[Option<bool>]) -> f64 {
+        match self.as_ref().value {
+            PCicruit::Variable { id, neg } => log_indicator(assignment[id.addr()], neg),
+            PCicruit::Product => {
+                self.left().log_eval(assignment) + self.right().log_eval(assignment)
+            }
+            PCicruit::Sum { left, right } => log_sum_exp(
+                left.log_prob() + self.left().log_eval(assignment),
+                right.log_prob() + self.right().log_eval(assignment),
+            ),
+            leaf => log_leaf(&leaf, assignment),
+        }
+    }
+}
+
+impl<W: Weight> LogEval<Option<bool>> for CircuitTree<W> {
+    fn log_eval(&self, assignment: &[Option<bool>]) -> f64 {
+        self.output().log_eval(assignment)
+    }
+}
+
+impl<W: Weight> LogEval<bool> for CircuitTree<W> {
+    fn log_eval(&self, assignment: &[bool]) -> f64 {
+        self.log_eval(&assignment.iter().map(|&x| Some(x)).collect::<Vec<_>>())
+    }
+}
+
+#[inline]
+fn indicator<T: Zero + One>(value: Option<bool>, neg: bool) -> T {
+    match value {
+        Some(value) if value == neg => T::zero(),
+        _ => T::one(),
+    }
+}
+
+#[inline]
+fn from_log<T: Float>(value: f64) -> T {
+    T::from(value.exp()).unwrap()
+}
+
+#[inline]
+fn log_indicator(value: Option<bool>, neg: bool) -> f64 {
+    match value {
+        Some(value) if value == neg => f64::NEG_INFINITY,
+        _ => 0.0,
+    }
+}
+
+// value of every node reachable from the output, indexed by node address
+pub fn eval_nodes<W: Weight>(circuit: &CircuitTree<W>, evidence: &[Option<bool>]) -> Vec<W::Value> {
+    let mut values = vec![W::Value::zero(); circuit.num_nodes()];
+    for idx in circuit.topological() {
+        let node = &circuit[idx];
+        let operands = node.node.operands();
+        values[idx.addr()] = match node.value {
+            PCicruit::Variable { id, neg } => indicator(evidence[id.addr()], neg),
+            PCicruit::Product => values[operands[0].addr()] * values[operands[1].addr()],
+            PCicruit::Sum { left, right } => {
+                left.value() * values[operands[0].addr()]
+                    + right.value() * values[operands[1].addr()]
+            }
+            leaf => from_log(log_leaf(&leaf, evidence)),
+        };
+    }
+    values
+}
+
+// same as eval_nodes but in log-space
+pub fn log_eval_nodes<W: Weight>(circuit: &CircuitTree<W>, evidence: &[Option<bool>]) -> Vec<f64> {
+    let mut values = vec![f64::NEG_INFINITY; circuit.num_nodes()];
+    for idx in circuit.topological() {
+        let node = &circuit[idx];
+        let operands = node.node.operands();
+        values[idx.addr()] = match node.value {
+            PCicruit::Variable { id, neg } => log_indicator(evidence[id.addr()], neg),
+            PCicruit::Product => values[operands[0].addr()] + values[operands[1].addr()],
+            PCicruit::Sum { left, right } => log_sum_exp(
+                left.log_prob() + values[operands[0].addr()],
+                right.log_prob() + values[operands[1].addr()],
+            ),
+            leaf => log_leaf(&leaf, evidence),
+        };
+    }
+    values
+}
+
+// change the storage of the sum weights, e.g. f32 to LogWeight
+pub fn convert_weights<W: Weight, V: Weight>(circuit: &CircuitTree<W>) -> CircuitTree<V> {
+    circuit.map(|value| match value {
+        PCicruit::Variable { id, neg } => PCicruit::Variable { id, neg },
+        PCicruit::Bernoulli { id, p } => PCicruit::Bernoulli {
+            id,
+            p: V::from_log_prob(p.log_prob()),
+        },
+        PCicruit::Indicator { id, value } => PCicruit::Indicator { id, value },
+        PCicruit::Gaussian { id, mean, variance } => PCicruit::Gaussian { id, mean, variance },
+        PCicruit::Product => PCicruit::Product,
+        PCicruit::Sum { left, right } => PCicruit::Sum {
+            left: V::from_log_prob(left.log_prob()),
+            right: V::from_log_prob(right.log_prob()),
+        },
+    })
+}
+
+impl<W: Weight> SemiringEval for CircuitTree<W> {
+    fn eval_semiring<S: Semiring, F: Fn(Addr, bool) -> S>(&self, leaf: F) -> S {
+        let mut values = vec![S::zero(); self.num_nodes()];
+        for idx in self.topological() {
+            let node = &self[idx];
+            let operands = node.node.operands();
+            values[idx.addr()] = match node.value {
+                PCicruit::Variable { id, neg } => leaf(id, neg),
+                PCicruit::Bernoulli { id, p } => S::weight(p)
+                    .times(&leaf(id, false))
+                    .plus(&S::weight(W::from_prob(1.0 - p.prob())).times(&leaf(id, true))),
+                // no boolean literal to weight, marginalised out
+                PCicruit::Indicator { .. } | PCicruit::Gaussian { .. } => S::one(),
+                PCicruit::Product => values[operands[0].addr()].times(&values[operands[1].addr()]),
+                PCicruit::Sum { left, right } => S::weight(left)
+                    .times(&values[operands[0].addr()])
+                    .plus(&S::weight(right).times(&values[operands[1].addr()])),
+            };
+        }
+        match self.output().idx.into() {
+            Some(output) => values.swap_remove(output),
+            None => S::zero(),
+        }
+    }
+}
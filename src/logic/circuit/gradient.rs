@@ -0,0 +1,70 @@
+use num_traits::ToPrimitive;
+
+use crate::tree::{Addr, LinkingNode, Mapping};
+
+use super::{eval_nodes, CircuitTree, PCicruit, Weight};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    // derivative of the output with respect to every node value, indexed by node address
+    pub nodes: Vec<f64>,
+    // derivative of the output with respect to the left and right weight of every sum
+    pub weights: Vec<[f64; 2]>,
+}
+
+// reverse-mode sweep reusing the values of a forward pass (eval_nodes)
+pub fn backward<W: Weight>(circuit: &CircuitTree<W>, values: &[W::Value]) -> Gradient {
+    let mut gradient = Gradient {
+        nodes: vec![0.0; circuit.num_nodes()],
+        weights: vec![[0.0; 2]; circuit.num_nodes()],
+    };
+    let output = circuit.output().idx;
+    if output.is_none() {
+        return gradient;
+    }
+    let value = |idx: Addr| values[idx.addr()].to_f64().unwrap();
+
+    gradient.nodes[output.addr()] = 1.0;
+    for idx in circuit.topological().into_iter().rev() {
+        let derivative = gradient.nodes[idx.addr()];
+        let operands = circuit[idx].node.operands();
+        match circuit[idx].value {
+            PCicruit::Product => {
+                gradient.nodes[operands[0].addr()] += derivative * value(operands[1]);
+                gradient.nodes[operands[1].addr()] += derivative * value(operands[0]);
+            }
+            PCicruit::Sum { left, right } => {
+                gradient.nodes[operands[0].addr()] += derivative * left.prob();
+                gradient.nodes[operands[1].addr()] += derivative * right.prob();
+                gradient.weights[idx.addr()] = [
+                    derivative * value(operands[0]),
+                    derivative * value(operands[1]),
+                ];
+            }
+            _ => {}
+        }
+    }
+    gradient
+}
+
+// differential approach: for each variable X the circuit value with X set to false and to true
+// while the rest of the evidence is kept, requires a smooth and decomposable circuit
+pub fn marginals<W: Weight>(circuit: &CircuitTree<W>, evidence: &[Option<bool>]) -> Vec<[f64; 2]> {
+    let values = eval_nodes(circuit, evidence);
+    let gradient = backward(circuit, &values);
+
+    let mut marginals = vec![[0.0; 2]; circuit.num_named()];
+    for idx in circuit.topological() {
+        match circuit[idx].value {
+            PCicruit::Variable { id, neg } => {
+                marginals[id.addr()][!neg as usize] += gradient.nodes[idx.addr()];
+            }
+            PCicruit::Bernoulli { id, p } => {
+                marginals[id.addr()][0] += gradient.nodes[idx.addr()] * (1.0 - p.prob());
+                marginals[id.addr()][1] += gradient.nodes[idx.addr()] * p.prob();
+            }
+            _ => {}
+        }
+    }
+    marginals
+}
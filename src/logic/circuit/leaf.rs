@@ -0,0 +1,239 @@
+use std::f64::consts::PI;
+
+use crate::{
+    random::Rng,
+    tree::{Addr, LinkingNode, Mapping},
+};
+
+use super::{log_sum_exp, CircuitTree, PCicruit, Weight};
+
+// smallest variance of a fitted Gaussian, keeps the density bounded
+const MIN_VARIANCE: f64 = 1e-6;
+
+// observations of mixed data are numbers: 0 and 1 for booleans, the index of
+// a category, or a real value; None marks a missing value
+impl<W: Weight> PCicruit<W> {
+    pub fn is_leaf(&self) -> bool {
+        !matches!(self, PCicruit::Product | PCicruit::Sum { .. })
+    }
+
+    // Variable and Bernoulli leaves take boolean values, the others only make
+    // sense on mixed data
+    pub fn is_boolean(&self) -> bool {
+        !matches!(self, PCicruit::Indicator { .. } | PCicruit::Gaussian { .. })
+    }
+
+    pub fn leaf_id(&self) -> Option<Addr> {
+        match *self {
+            PCicruit::Variable { id, .. }
+            | PCicruit::Bernoulli { id, .. }
+            | PCicruit::Indicator { id, .. }
+            | PCicruit::Gaussian { id, .. } => Some(id),
+            PCicruit::Product | PCicruit::Sum { .. } => None,
+        }
+    }
+
+    // log density of a leaf, unobserved values are marginalised out
+    pub fn log_density(&self, value: Option<f64>) -> f64 {
+        let Some(x) = value else {
+            return 0.0;
+        };
+        match *self {
+            PCicruit::Variable { neg, .. } => {
+                if (x != 0.0) == neg {
+                    f64::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            }
+            PCicruit::Bernoulli { p, .. } => {
+                if x != 0.0 {
+                    p.log_prob()
+                } else {
+                    (1.0 - p.prob()).ln()
+                }
+            }
+            PCicruit::Indicator { value, .. } => {
+                if x == value as f64 {
+                    0.0
+                } else {
+                    f64::NEG_INFINITY
+                }
+            }
+            PCicruit::Gaussian { mean, variance, .. } => {
+                -0.5 * ((x - mean).powi(2) / variance + (2.0 * PI * variance).ln())
+            }
+            PCicruit::Product | PCicruit::Sum { .. } => panic!("Not a leaf"),
+        }
+    }
+
+    pub fn sample_leaf(&self, rng: &mut Rng) -> f64 {
+        match *self {
+            PCicruit::Variable { neg, .. } => !neg as u8 as f64,
+            PCicruit::Bernoulli { p, .. } => (rng.next_f64() < p.prob()) as u8 as f64,
+            PCicruit::Indicator { value, .. } => value as f64,
+            PCicruit::Gaussian { mean, variance, .. } => mean + variance.sqrt() * rng.next_normal(),
+            PCicruit::Product | PCicruit::Sum { .. } => panic!("Not a leaf"),
+        }
+    }
+
+    // maximum likelihood parameters from (weight, value) observations, the
+    // Bernoulli counts are smoothed like the sum weights; leaves without
+    // parameters or without observations are kept
+    pub fn fit_leaf(&self, observations: &[(f64, f64)], smoothing: f64) -> Self {
+        let total: f64 = observations.iter().map(|x| x.0).sum();
+        match *self {
+            PCicruit::Bernoulli { id, p } => {
+                let ones: f64 = observations
+                    .iter()
+                    .filter(|x| x.1 != 0.0)
+                    .map(|x| x.0)
+                    .sum();
+                if total + 2.0 * smoothing <= 0.0 {
+                    return PCicruit::Bernoulli { id, p };
+                }
+                PCicruit::Bernoulli {
+                    id,
+                    p: W::from_prob((ones + smoothing) / (total + 2.0 * smoothing)),
+                }
+            }
+            PCicruit::Gaussian { id, .. } if total > 0.0 => {
+                let mean = observations.iter().map(|x| x.0 * x.1).sum::<f64>() / total;
+                let variance = observations
+                    .iter()
+                    .map(|x| x.0 * (x.1 - mean).powi(2))
+                    .sum::<f64>()
+                    / total;
+                PCicruit::Gaussian {
+                    id,
+                    mean,
+                    variance: variance.max(MIN_VARIANCE),
+                }
+            }
+            leaf => leaf,
+        }
+    }
+
+    // observations standing for a missing value in maximum likelihood, they
+    // have the expected sufficient statistics of the leaf
+    pub(super) fn expected_observations(&self, weight: f64) -> Vec<(f64, f64)> {
+        match *self {
+            PCicruit::Bernoulli { p, .. } => {
+                vec![(weight * p.prob(), 1.0), (weight * (1.0 - p.prob()), 0.0)]
+            }
+            PCicruit::Gaussian { mean, variance, .. } => {
+                let deviation = variance.sqrt();
+                vec![
+                    (weight / 2.0, mean - deviation),
+                    (weight / 2.0, mean + deviation),
+                ]
+            }
+            _ => Default::default(),
+        }
+    }
+}
+
+// leaf value under boolean evidence, leaves that are not boolean can only be
+// marginalised out
+#[inline]
+pub(super) fn log_leaf<W: Weight>(leaf: &PCicruit<W>, evidence: &[Option<bool>]) -> f64 {
+    log_boolean_leaf(leaf, evidence[leaf.leaf_id().unwrap().addr()])
+}
+
+#[inline]
+pub(super) fn log_boolean_leaf<W: Weight>(leaf: &PCicruit<W>, value: Option<bool>) -> f64 {
+    match value {
+        None => 0.0,
+        Some(_) if !leaf.is_boolean() => {
+            panic!("Non-boolean leaf observed as a boolean, use log_eval_mixed")
+        }
+        Some(x) => leaf.log_density(Some(x as u8 as f64)),
+    }
+}
+
+// the boolean entry points that can fail reject circuits with categorical or
+// Gaussian leaves, mixed data goes through log_eval_mixed
+pub(super) fn check_boolean<W: Weight>(circuit: &CircuitTree<W>) -> Result<(), &'static str> {
+    match circuit
+        .topological()
+        .iter()
+        .all(|&idx| circuit[idx].value.is_boolean())
+    {
+        true => Ok(()),
+        false => Err("Circuit has non-boolean leaves, use log_eval_mixed"),
+    }
+}
+
+// log value of every node reachable from the output under mixed evidence
+pub fn log_eval_mixed<W: Weight>(circuit: &CircuitTree<W>, evidence: &[Option<f64>]) -> Vec<f64> {
+    let mut values = vec![f64::NEG_INFINITY; circuit.num_nodes()];
+    for idx in circuit.topological() {
+        let node = &circuit[idx];
+        let operands = node.node.operands();
+        values[idx.addr()] = match node.value {
+            PCicruit::Product => values[operands[0].addr()] + values[operands[1].addr()],
+            PCicruit::Sum { left, right } => log_sum_exp(
+                left.log_prob() + values[operands[0].addr()],
+                right.log_prob() + values[operands[1].addr()],
+            ),
+            leaf => leaf.log_density(evidence[leaf.leaf_id().unwrap().addr()]),
+        };
+    }
+    values
+}
+
+// log-likelihood of a sample of mixed data, normalised by the partition function
+pub fn log_likelihood_mixed<W: Weight>(circuit: &CircuitTree<W>, sample: &[Option<f64>]) -> f64 {
+    let output = circuit.output().idx;
+    if output.is_none() {
+        return f64::NEG_INFINITY;
+    }
+    let partition = log_eval_mixed(circuit, &vec![None; circuit.num_named()])[output.addr()];
+    log_eval_mixed(circuit, sample)[output.addr()] - partition
+}
+
+// ancestral sampling of mixed data, the evidence is kept and the variables
+// outside of the sampled leaves are NaN
+pub fn sample_mixed<W: Weight>(
+    circuit: &CircuitTree<W>,
+    evidence: &[Option<f64>],
+    rng: &mut Rng,
+    n: usize,
+) -> Result<Vec<Vec<f64>>, &'static str> {
+    let values = log_eval_mixed(circuit, evidence);
+    if circuit.output().idx.is_none() || values[circuit.output().idx.addr()] == f64::NEG_INFINITY {
+        return Err("Evidence has zero probability");
+    }
+
+    Ok((0..n)
+        .map(|_| {
+            let mut sample: Vec<f64> = evidence.iter().map(|x| x.unwrap_or(f64::NAN)).collect();
+            let mut stack: Vec<Addr> = vec![circuit.output().idx];
+            while let Some(idx) = stack.pop() {
+                let operands = circuit[idx].node.operands();
+                match circuit[idx].value {
+                    PCicruit::Product => {
+                        stack.push(operands[0]);
+                        stack.push(operands[1]);
+                    }
+                    PCicruit::Sum { left, right } => {
+                        let left = left.log_prob() + values[operands[0].addr()];
+                        let right = right.log_prob() + values[operands[1].addr()];
+                        if rng.next_f64() < (left - log_sum_exp(left, right)).exp() {
+                            stack.push(operands[0]);
+                        } else {
+                            stack.push(operands[1]);
+                        }
+                    }
+                    leaf => {
+                        let id = leaf.leaf_id().unwrap().addr();
+                        if evidence[id].is_none() {
+                            sample[id] = leaf.sample_leaf(rng);
+                        }
+                    }
+                }
+            }
+            sample
+        })
+        .collect())
+}
@@ -0,0 +1,349 @@
+use crate::{
+    random::Rng,
+    tree::{Addr, IndexedMutRef, Mapping},
+};
+
+use super::{PCMut, ProbabilisticCircuitTree};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Independence {
+    // G statistic of the pair against a chi-squared critical value with one
+    // degree of freedom, 10.83 for a significance of 0.001
+    GTest { critical: f64 },
+    // pairwise mutual information in nats
+    MutualInformation { threshold: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clustering {
+    // hard assignment to the closest centroid
+    KMeans,
+    // mixture of independent Bernoullis, samples go to their most likely component
+    Em,
+}
+
+// LearnSPN on boolean data: variables split into independent groups give
+// products, otherwise the samples are clustered into a sum
+#[derive(Debug, Clone)]
+pub struct LearnSpn {
+    pub independence: Independence,
+    pub clustering: Clustering,
+    pub clusters: usize,
+    pub iterations: usize,
+    // fewer samples than this give a fully factorised product
+    pub min_samples: usize,
+    // Laplace smoothing of the leaf probabilities
+    pub smoothing: f64,
+}
+
+impl Default for LearnSpn {
+    fn default() -> Self {
+        LearnSpn {
+            independence: Independence::GTest { critical: 10.83 },
+            clustering: Clustering::KMeans,
+            clusters: 2,
+            iterations: 20,
+            min_samples: 20,
+            smoothing: 1.0,
+        }
+    }
+}
+
+// learned structure before it is written to the circuit
+enum Region {
+    Leaf { var: usize, p: f64 },
+    Product(Vec<Region>),
+    Sum(Vec<(f64, Region)>),
+}
+
+// mutual information in nats of two booleans from their joint counts
+pub(super) fn mutual_information(counts: &[[f64; 2]; 2]) -> f64 {
+    let n: f64 = counts.iter().flatten().sum();
+    let left = [counts[0][0] + counts[0][1], counts[1][0] + counts[1][1]];
+    let right = [counts[0][0] + counts[1][0], counts[0][1] + counts[1][1]];
+    let mut result = 0.0;
+    for x in 0..2 {
+        for y in 0..2 {
+            if counts[x][y] > 0.0 {
+                result += counts[x][y] / n * (counts[x][y] * n / (left[x] * right[y])).ln();
+            }
+        }
+    }
+    result
+}
+
+fn squared_distance(sample: &[bool], vars: &[usize], centroid: &[f64]) -> f64 {
+    vars.iter()
+        .zip(centroid.iter())
+        .map(|(&var, &c)| (sample[var] as u8 as f64 - c).powi(2))
+        .sum()
+}
+
+impl LearnSpn {
+    // samples are rows of the same length, variable i of the circuit is column i
+    pub fn learn(
+        &self,
+        data: &[Vec<bool>],
+        rng: &mut Rng,
+    ) -> Result<ProbabilisticCircuitTree, &'static str> {
+        if data.is_empty() {
+            return Err("No samples");
+        }
+        let num_variables = data[0].len();
+        if data.iter().any(|sample| sample.len() != num_variables) {
+            return Err("Samples have different lengths");
+        }
+        if num_variables == 0 {
+            return Err("No variables");
+        }
+        if self.clusters < 2 {
+            return Err("At least two clusters are needed");
+        }
+
+        let rows: Vec<usize> = (0..data.len()).collect();
+        let vars: Vec<usize> = (0..num_variables).collect();
+        let region = self.region(data, &rows, &vars, rng);
+
+        let mut tree: ProbabilisticCircuitTree = Default::default();
+        for _ in 0..num_variables {
+            tree.add_anon();
+        }
+        tree.builder(|builder| emit(builder, &region));
+        Ok(tree)
+    }
+
+    fn region(&self, data: &[Vec<bool>], rows: &[usize], vars: &[usize], rng: &mut Rng) -> Region {
+        if vars.len() == 1 {
+            return self.leaf(data, rows, vars[0]);
+        }
+        if rows.len() < self.min_samples {
+            return self.factorise(data, rows, vars);
+        }
+
+        let components = self.independent_groups(data, rows, vars);
+        if components.len() > 1 {
+            return Region::Product(
+                components
+                    .iter()
+                    .map(|group| self.region(data, rows, group, rng))
+                    .collect(),
+            );
+        }
+
+        let assignment = match self.clustering {
+            Clustering::KMeans => self.k_means(data, rows, vars, rng),
+            Clustering::Em => self.em(data, rows, vars, rng),
+        };
+        let mut clusters: Vec<Vec<usize>> = vec![Default::default(); self.clusters];
+        for (&row, &cluster) in rows.iter().zip(assignment.iter()) {
+            clusters[cluster].push(row);
+        }
+        clusters.retain(|cluster| !cluster.is_empty());
+        if clusters.len() < 2 {
+            return self.factorise(data, rows, vars);
+        }
+
+        Region::Sum(
+            clusters
+                .iter()
+                .map(|cluster| {
+                    (
+                        cluster.len() as f64 / rows.len() as f64,
+                        self.region(data, cluster, vars, rng),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn leaf(&self, data: &[Vec<bool>], rows: &[usize], var: usize) -> Region {
+        let ones = rows.iter().filter(|&&row| data[row][var]).count() as f64;
+        Region::Leaf {
+            var,
+            p: (ones + self.smoothing) / (rows.len() as f64 + 2.0 * self.smoothing),
+        }
+    }
+
+    fn factorise(&self, data: &[Vec<bool>], rows: &[usize], vars: &[usize]) -> Region {
+        Region::Product(vars.iter().map(|&var| self.leaf(data, rows, var)).collect())
+    }
+
+    fn dependent(&self, data: &[Vec<bool>], rows: &[usize], a: usize, b: usize) -> bool {
+        let mut counts = [[0.0f64; 2]; 2];
+        for &row in rows.iter() {
+            counts[data[row][a] as usize][data[row][b] as usize] += 1.0;
+        }
+        let information = mutual_information(&counts);
+        match self.independence {
+            Independence::GTest { critical } => 2.0 * rows.len() as f64 * information > critical,
+            Independence::MutualInformation { threshold } => information > threshold,
+        }
+    }
+
+    // connected components of the graph linking pairwise dependent variables
+    fn independent_groups(
+        &self,
+        data: &[Vec<bool>],
+        rows: &[usize],
+        vars: &[usize],
+    ) -> Vec<Vec<usize>> {
+        let mut component: Vec<Option<usize>> = vec![None; vars.len()];
+        let mut groups: Vec<Vec<usize>> = Default::default();
+        for start in 0..vars.len() {
+            if component[start].is_some() {
+                continue;
+            }
+            component[start] = Some(groups.len());
+            let mut group = vec![vars[start]];
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for j in 0..vars.len() {
+                    if component[j].is_none() && self.dependent(data, rows, vars[i], vars[j]) {
+                        component[j] = Some(groups.len());
+                        group.push(vars[j]);
+                        stack.push(j);
+                    }
+                }
+            }
+            group.sort();
+            groups.push(group);
+        }
+        groups
+    }
+
+    // cluster of every row, centroids start at distinct random samples
+    fn k_means(
+        &self,
+        data: &[Vec<bool>],
+        rows: &[usize],
+        vars: &[usize],
+        rng: &mut Rng,
+    ) -> Vec<usize> {
+        let mut centroids: Vec<Vec<f64>> = Default::default();
+        let mut candidates = rows.to_vec();
+        while centroids.len() < self.clusters && !candidates.is_empty() {
+            let row = candidates.swap_remove(rng.below(candidates.len()));
+            let centroid: Vec<f64> = vars
+                .iter()
+                .map(|&var| data[row][var] as u8 as f64)
+                .collect();
+            if !centroids.contains(&centroid) {
+                centroids.push(centroid);
+            }
+        }
+
+        let mut assignment = vec![0; rows.len()];
+        for _ in 0..self.iterations {
+            let mut changed = false;
+            for (i, &row) in rows.iter().enumerate() {
+                let closest = (0..centroids.len())
+                    .min_by(|&a, &b| {
+                        squared_distance(&data[row], vars, &centroids[a])
+                            .total_cmp(&squared_distance(&data[row], vars, &centroids[b]))
+                    })
+                    .unwrap();
+                changed |= closest != assignment[i];
+                assignment[i] = closest;
+            }
+
+            let mut sums = vec![vec![0.0f64; vars.len()]; centroids.len()];
+            let mut sizes = vec![0usize; centroids.len()];
+            for (i, &row) in rows.iter().enumerate() {
+                sizes[assignment[i]] += 1;
+                for (sum, &var) in sums[assignment[i]].iter_mut().zip(vars.iter()) {
+                    *sum += data[row][var] as u8 as f64;
+                }
+            }
+            for (cluster, centroid) in centroids.iter_mut().enumerate() {
+                if sizes[cluster] > 0 {
+                    for (c, sum) in centroid.iter_mut().zip(sums[cluster].iter()) {
+                        *c = sum / sizes[cluster] as f64;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        assignment
+    }
+
+    // most likely component of every row in a mixture of independent
+    // Bernoullis, started from a random partition of the rows
+    fn em(&self, data: &[Vec<bool>], rows: &[usize], vars: &[usize], rng: &mut Rng) -> Vec<usize> {
+        let k = self.clusters;
+        let mut responsibilities: Vec<Vec<f64>> = rows
+            .iter()
+            .map(|_| {
+                let mut r = vec![0.0; k];
+                r[rng.below(k)] = 1.0;
+                r
+            })
+            .collect();
+
+        for _ in 0..self.iterations {
+            // maximisation with smoothed parameters
+            let mut priors = vec![0.0f64; k];
+            let mut probs = vec![vec![0.0f64; vars.len()]; k];
+            for (r, &row) in responsibilities.iter().zip(rows.iter()) {
+                for c in 0..k {
+                    priors[c] += r[c];
+                    for (p, &var) in probs[c].iter_mut().zip(vars.iter()) {
+                        if data[row][var] {
+                            *p += r[c];
+                        }
+                    }
+                }
+            }
+            for c in 0..k {
+                for p in probs[c].iter_mut() {
+                    *p = (*p + self.smoothing) / (priors[c] + 2.0 * self.smoothing);
+                }
+                priors[c] =
+                    (priors[c] + self.smoothing) / (rows.len() as f64 + k as f64 * self.smoothing);
+            }
+
+            // expectation in log space
+            for (r, &row) in responsibilities.iter_mut().zip(rows.iter()) {
+                let logs: Vec<f64> = (0..k)
+                    .map(|c| {
+                        priors[c].ln()
+                            + vars
+                                .iter()
+                                .zip(probs[c].iter())
+                                .map(|(&var, &p)| {
+                                    if data[row][var] {
+                                        p.ln()
+                                    } else {
+                                        (1.0 - p).ln()
+                                    }
+                                })
+                                .sum::<f64>()
+                    })
+                    .collect();
+                let max = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let total: f64 = logs.iter().map(|x| (x - max).exp()).sum();
+                for c in 0..k {
+                    r[c] = (logs[c] - max).exp() / total;
+                }
+            }
+        }
+
+        responsibilities
+            .iter()
+            .map(|r| (0..k).max_by(|&a, &b| r[a].total_cmp(&r[b])).unwrap())
+            .collect()
+    }
+}
+
+fn emit(builder: &mut IndexedMutRef<ProbabilisticCircuitTree>, region: &Region) -> Addr {
+    match region {
+        Region::Leaf { var, p } => builder.bernoulli(Addr::new(*var), *p as f32),
+        Region::Product(children) => {
+            builder.prod_n(&mut children.iter(), |builder, child| emit(builder, child))
+        }
+        Region::Sum(children) => builder.sum_n(&mut children.iter(), |builder, (weight, child)| {
+            (emit(builder, child), *weight as f32)
+        }),
+    }
+}
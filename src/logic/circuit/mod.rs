@@ -1,14 +1,39 @@
+pub mod batch;
 pub mod builder;
+pub mod chow_liu;
 pub mod compile;
+pub mod compiled;
+pub mod ddnnf;
 pub mod eval;
+pub mod gradient;
+pub mod leaf;
+pub mod learn;
+pub mod multiply;
 pub mod node;
+pub mod parallel;
+pub mod parameters;
+pub mod sample;
+pub mod weight;
 
 #[cfg(test)]
 mod tests;
 
+pub use batch::*;
 pub use builder::*;
+pub use chow_liu::*;
 pub use compile::*;
+pub use compiled::*;
+pub use ddnnf::*;
+pub use eval::*;
+pub use gradient::*;
+pub use leaf::*;
+pub use learn::*;
+pub use multiply::*;
 pub use node::*;
+pub use parallel::*;
+pub use parameters::*;
+pub use sample::*;
+pub use weight::*;
 
 use crate::tree::{Addr, Node, Tree};
 
@@ -16,15 +41,22 @@ use super::Semantic;
 
 pub use PCicruit as ProbabilisticCircuit;
 pub type ProbabilisticCircuitTree = Tree<PCicruit, 2>;
+pub type CircuitTree<W> = Tree<PCicruit<W>, 2>;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum PCicruit {
+pub enum PCicruit<W = f32> {
     Variable { id: Addr, neg: bool },
+    // P(X = true) = p
+    Bernoulli { id: Addr, p: W },
+    // X takes the value, a categorical distribution is a weighted sum of them
+    Indicator { id: Addr, value: u32 },
+    // density of a continuous X
+    Gaussian { id: Addr, mean: f64, variance: f64 },
     Product,
-    Sum { left: f32, right: f32 },
+    Sum { left: W, right: W },
 }
 
-impl Semantic for PCicruit {
-    type Tree = Tree<PCicruit, 2>;
+impl<W: Weight> Semantic for PCicruit<W> {
+    type Tree = Tree<PCicruit<W>, 2>;
     type Node = Node<2>;
 }
@@ -0,0 +1,295 @@
+use std::{cell::Cell, collections::HashMap, f64::consts::PI};
+
+use crate::tree::{Addr, IndexedMutRef, LinkingNode, Mapping, NodeAllocator};
+
+use super::{PCMut, PCicruit, ProbabilisticCircuitTree, Weight};
+
+// sorted variables below every reachable node, renamed through ids
+fn scopes(circuit: &ProbabilisticCircuitTree, ids: &[usize]) -> Vec<Vec<usize>> {
+    let mut scopes: Vec<Vec<usize>> = vec![Default::default(); circuit.num_nodes()];
+    for idx in circuit.topological() {
+        let node = &circuit[idx];
+        scopes[idx.addr()] = match node.value.leaf_id() {
+            Some(id) => vec![ids[id.addr()]],
+            None => {
+                let operands = node.node.operands();
+                let mut scope = scopes[operands[0].addr()].clone();
+                scope.extend(scopes[operands[1].addr()].iter().copied());
+                scope.sort();
+                scope.dedup();
+                scope
+            }
+        };
+    }
+    scopes
+}
+
+// operands of a chain of products, whatever the way they are associated
+fn factors(circuit: &ProbabilisticCircuitTree, idx: Addr, result: &mut Vec<Addr>) {
+    match circuit[idx].value {
+        PCicruit::Product => {
+            for &child in circuit[idx].node.operands() {
+                factors(circuit, child, result);
+            }
+        }
+        _ => result.push(idx),
+    }
+}
+
+// id of every variable of the second circuit in the first one, matched by
+// name when both circuits name it and by position otherwise
+fn variables(
+    first: &ProbabilisticCircuitTree,
+    second: &ProbabilisticCircuitTree,
+) -> Result<Vec<usize>, &'static str> {
+    let mut ids: Vec<usize> = Default::default();
+    for (position, name) in second.named.iter().enumerate() {
+        let id = match name {
+            Some(name) => first.get_id(name),
+            None => Addr::NONE,
+        };
+        ids.push(match id.is_none() {
+            false => id.addr(),
+            true if first.get_named(Addr::new(position)).is_none() => position,
+            true => return Err("Variables do not match"),
+        });
+    }
+    let mut sorted = ids.clone();
+    sorted.sort();
+    sorted.dedup();
+    if sorted.len() != ids.len() {
+        return Err("Variables do not match");
+    }
+    Ok(ids)
+}
+
+// product of two leaves over the same variable, as a leaf with the id of the
+// first one and a constant factor
+fn leaf_product(a: PCicruit, b: PCicruit) -> Result<(PCicruit, f64), &'static str> {
+    Ok(match (a, b) {
+        (PCicruit::Variable { neg: x, .. }, PCicruit::Variable { neg: y, .. }) => {
+            (a, (x == y) as u8 as f64)
+        }
+        (PCicruit::Variable { id, neg }, PCicruit::Bernoulli { p, .. })
+        | (PCicruit::Bernoulli { id, p }, PCicruit::Variable { neg, .. }) => {
+            let lit = PCicruit::Variable { id, neg };
+            (lit, if neg { 1.0 - p.prob() } else { p.prob() })
+        }
+        (PCicruit::Bernoulli { id, p }, PCicruit::Bernoulli { p: q, .. }) => {
+            let (p, q) = (p.prob(), q.prob());
+            let total = p * q + (1.0 - p) * (1.0 - q);
+            if total == 0.0 {
+                return Ok((a, 0.0));
+            }
+            let p = Weight::from_prob(p * q / total);
+            (PCicruit::Bernoulli { id, p }, total)
+        }
+        (PCicruit::Indicator { value: x, .. }, PCicruit::Indicator { value: y, .. }) => {
+            (a, (x == y) as u8 as f64)
+        }
+        (
+            PCicruit::Gaussian {
+                id,
+                mean: m1,
+                variance: v1,
+            },
+            PCicruit::Gaussian {
+                mean: m2,
+                variance: v2,
+                ..
+            },
+        ) => {
+            let variance = v1 + v2;
+            let scale =
+                (-(m1 - m2).powi(2) / (2.0 * variance)).exp() / (2.0 * PI * variance).sqrt();
+            let leaf = PCicruit::Gaussian {
+                id,
+                mean: (m1 * v2 + m2 * v1) / variance,
+                variance: v1 * v2 / variance,
+            };
+            (leaf, scale)
+        }
+        _ => return Err("Leaves of different kinds"),
+    })
+}
+
+struct Multiplier<'a, 'b, 'c> {
+    builder: &'a mut IndexedMutRef<'b, ProbabilisticCircuitTree>,
+    circuits: [&'c ProbabilisticCircuitTree; 2],
+    scopes: [Vec<Vec<usize>>; 2],
+    cache: HashMap<(Addr, Addr), (Addr, f64)>,
+}
+
+impl<'a, 'b, 'c> Multiplier<'a, 'b, 'c> {
+    // the product is the node times a constant factor, which is folded into
+    // the weights of the enclosing sum
+    fn multiply(&mut self, a: Addr, b: Addr) -> Result<(Addr, f64), &'static str> {
+        if let Some(&result) = self.cache.get(&(a, b)) {
+            return Ok(result);
+        }
+        if self.scopes[0][a.addr()] != self.scopes[1][b.addr()] {
+            return Err("Circuits are not compatible");
+        }
+
+        let [first, second] = self.circuits;
+        let (left, right) = (first[a].node.operands(), second[b].node.operands());
+        let result = match (first[a].value, second[b].value) {
+            (
+                PCicruit::Sum {
+                    left: w1,
+                    right: w2,
+                },
+                PCicruit::Sum {
+                    left: v1,
+                    right: v2,
+                },
+            ) => {
+                let mut terms: Vec<(Addr, f32)> = Default::default();
+                for (&x, w) in left.iter().zip([w1, w2]) {
+                    for (&y, v) in right.iter().zip([v1, v2]) {
+                        let (term, scale) = self.multiply(x, y)?;
+                        terms.push((term, (w as f64 * v as f64 * scale) as f32));
+                    }
+                }
+                let sum = self.builder.sum_n(&mut terms.into_iter(), |_, term| term);
+                (sum, 1.0)
+            }
+            (
+                PCicruit::Sum {
+                    left: w1,
+                    right: w2,
+                },
+                _,
+            ) => {
+                let (x, sx) = self.multiply(left[0], b)?;
+                let (y, sy) = self.multiply(left[1], b)?;
+                let (w1, w2) = ((w1 as f64 * sx) as f32, (w2 as f64 * sy) as f32);
+                (self.builder.sum_w(w1, |_| x, w2, |_| y), 1.0)
+            }
+            (
+                _,
+                PCicruit::Sum {
+                    left: v1,
+                    right: v2,
+                },
+            ) => {
+                let (x, sx) = self.multiply(a, right[0])?;
+                let (y, sy) = self.multiply(a, right[1])?;
+                let (v1, v2) = ((v1 as f64 * sx) as f32, (v2 as f64 * sy) as f32);
+                (self.builder.sum_w(v1, |_| x, v2, |_| y), 1.0)
+            }
+            (PCicruit::Product, PCicruit::Product) => {
+                let (parts, scale) = self.parts(a, b)?;
+                let product = self.builder.prod_n(&mut parts.into_iter(), |_, part| part);
+                (product, scale)
+            }
+            (PCicruit::Product, _) | (_, PCicruit::Product) => {
+                return Err("Circuits are not compatible")
+            }
+            (x, y) => {
+                let (leaf, scale) = leaf_product(x, y)?;
+                (self.builder.array.push(leaf, &[]), scale)
+            }
+        };
+
+        self.cache.insert((a, b), result);
+        Ok(result)
+    }
+
+    // products of the factors of two products, the factors have to split the
+    // scope in the same way
+    fn parts(&mut self, a: Addr, b: Addr) -> Result<(Vec<Addr>, f64), &'static str> {
+        let (mut xs, mut ys) = (Vec::new(), Vec::new());
+        factors(self.circuits[0], a, &mut xs);
+        factors(self.circuits[1], b, &mut ys);
+        if xs.len() != ys.len() {
+            return Err("Circuits are not compatible");
+        }
+        let mut parts: Vec<Addr> = Default::default();
+        let mut scale = 1.0;
+        for x in xs {
+            let y = *ys
+                .iter()
+                .find(|y| self.scopes[1][y.addr()] == self.scopes[0][x.addr()])
+                .ok_or("Circuits are not compatible")?;
+            let (part, factor) = self.multiply(x, y)?;
+            parts.push(part);
+            scale *= factor;
+        }
+        Ok((parts, scale))
+    }
+
+    // the constant factor left at the output goes into the weights of a sum
+    // among the factors of the output, nothing else refers to that sum since
+    // the factors have disjoint scopes
+    fn output(&mut self, a: Addr, b: Addr) -> Result<Addr, &'static str> {
+        let [first, second] = self.circuits;
+        let (parts, scale) = match (first[a].value, second[b].value) {
+            (PCicruit::Product, PCicruit::Product) => self.parts(a, b)?,
+            _ => {
+                let (addr, scale) = self.multiply(a, b)?;
+                (vec![addr], scale)
+            }
+        };
+        let tree = &mut self.builder.array;
+        for &part in parts.iter() {
+            if let PCicruit::Sum { left, right } = tree[part].value {
+                tree[part].value = PCicruit::Sum {
+                    left: (left as f64 * scale) as f32,
+                    right: (right as f64 * scale) as f32,
+                };
+                break;
+            }
+        }
+        Ok(self.builder.prod_n(&mut parts.into_iter(), |_, part| part))
+    }
+}
+
+// pointwise product of two smooth circuits that are structured decomposable
+// with the same vtree, every product has to split its scope like the product
+// of the other circuit over the same variables; variables named in both
+// circuits are matched by name, the others by position, and the names are
+// taken from the first circuit
+//
+// the result is exact when the output or one of the factors of the output is
+// a sum, otherwise, as for a product of leaves, it is unnormalised and only
+// proportional to the product
+//
+// circuits compiled from first-order formulas are only compatible when they
+// are smooth, disjunctions and existentials give sums over different scopes
+pub fn multiply(
+    first: &ProbabilisticCircuitTree,
+    second: &ProbabilisticCircuitTree,
+) -> Result<ProbabilisticCircuitTree, &'static str> {
+    if first.output().idx.is_none() || second.output().idx.is_none() {
+        return Err("Empty circuit");
+    }
+    let ids = variables(first, second)?;
+
+    let mut result: ProbabilisticCircuitTree = Default::default();
+    result.copy_named(first);
+    let num_variables = ids.iter().map(|id| id + 1).max().unwrap_or(0);
+    for _ in first.num_named()..num_variables {
+        result.add_anon();
+    }
+    let identity: Vec<usize> = (0..first.num_named()).collect();
+    let error: Cell<Option<&'static str>> = Cell::new(None);
+    result.builder(|builder| {
+        let mut multiplier = Multiplier {
+            builder,
+            circuits: [first, second],
+            scopes: [scopes(first, &identity), scopes(second, &ids)],
+            cache: Default::default(),
+        };
+        multiplier
+            .output(first.output().idx, second.output().idx)
+            .unwrap_or_else(|e| {
+                error.set(Some(e));
+                Addr::NONE
+            })
+    });
+    match error.get() {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
@@ -1,20 +1,21 @@
 use std::{fmt::Display, ops::Index};
 
 use crate::{
-    logic::{Semantic, SemanticNode},
+    logic::SemanticNode,
     tree::{Addr, IndexedRef, LinkingNode, Mapping, Node, NodeValue, Tree},
 };
 
-use super::PCicruit;
+use super::{PCicruit, Weight};
 
 pub trait PCRef {
     fn left(&self) -> Self;
     fn right(&self) -> Self;
 }
 
-impl<'a, T> PCRef for IndexedRef<'a, T>
+impl<'a, T, W> PCRef for IndexedRef<'a, T>
 where
-    T: Index<Addr, Output = NodeValue<Node<2>, PCicruit>>,
+    T: Index<Addr, Output = NodeValue<Node<2>, PCicruit<W>>>,
+    W: Weight,
 {
     fn left(&self) -> Self {
         IndexedRef {
@@ -31,13 +32,13 @@ where
     }
 }
 
-impl Display for Tree<PCicruit, 2> {
+impl<W: Weight> Display for Tree<PCicruit<W>, 2> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Display::fmt(&self.output(), f)
     }
 }
 
-impl<'a> Display for IndexedRef<'a, <PCicruit as Semantic>::Tree> {
+impl<'a, W: Weight> Display for IndexedRef<'a, Tree<PCicruit<W>, 2>> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.as_ref().value {
             PCicruit::Variable { id, neg } => {
@@ -48,6 +49,21 @@ impl<'a> Display for IndexedRef<'a, <PCicruit as Semantic>::Tree> {
                     self.array.fmt_named(id)
                 )
             }
+            PCicruit::Bernoulli { id, p } => {
+                write!(f, "B({}; {:.3})", self.array.fmt_named(id), p.prob())
+            }
+            PCicruit::Indicator { id, value } => {
+                write!(f, "[{}={}]", self.array.fmt_named(id), value)
+            }
+            PCicruit::Gaussian { id, mean, variance } => {
+                write!(
+                    f,
+                    "N({}; {:.3}, {:.3})",
+                    self.array.fmt_named(id),
+                    mean,
+                    variance
+                )
+            }
             PCicruit::Product => {
                 write!(f, "(")?;
                 Display::fmt(&self.left(), f)?;
@@ -57,13 +73,13 @@ impl<'a> Display for IndexedRef<'a, <PCicruit as Semantic>::Tree> {
             }
             PCicruit::Sum { left, right } => {
                 write!(f, "(")?;
-                if left != 1.0 {
-                    write!(f, "{left:.3}\u{2219}")?;
+                if left != W::ONE {
+                    write!(f, "{:.3}\u{2219}", left.prob())?;
                 }
                 Display::fmt(&self.left(), f)?;
                 write!(f, "+")?;
-                if right != 1.0 {
-                    write!(f, "{right:.3}\u{2219}")?;
+                if right != W::ONE {
+                    write!(f, "{:.3}\u{2219}", right.prob())?;
                 }
                 Display::fmt(&self.right(), f)?;
                 write!(f, ")")
@@ -72,10 +88,13 @@ impl<'a> Display for IndexedRef<'a, <PCicruit as Semantic>::Tree> {
     }
 }
 
-impl SemanticNode for NodeValue<Node<2>, PCicruit> {
+impl<W: Weight> SemanticNode for NodeValue<Node<2>, PCicruit<W>> {
     fn arity(&self) -> usize {
         match self.value {
-            PCicruit::Variable { .. } => 0,
+            PCicruit::Variable { .. }
+            | PCicruit::Bernoulli { .. }
+            | PCicruit::Indicator { .. }
+            | PCicruit::Gaussian { .. } => 0,
             PCicruit::Product => 2,
             PCicruit::Sum { .. } => 2,
         }
@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Barrier;
+use std::thread;
+
+use super::{batch::log_eval_range, check_boolean, Batch, CircuitTree, CompiledCircuit, Weight};
+
+// splits the samples in contiguous blocks, one per thread, every sample goes through the same
+// operations as in log_eval_batch so the results are bit-identical
+pub fn par_log_eval_batch<W: Weight + Sync>(
+    circuit: &CircuitTree<W>,
+    batch: &Batch,
+    num_threads: usize,
+) -> Result<Vec<f64>, &'static str> {
+    check_boolean(circuit)?;
+    let order = circuit.topological();
+    let mut result = vec![f64::NEG_INFINITY; batch.num_samples];
+    let block = batch.num_samples.div_ceil(num_threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        for (idx, slice) in result.chunks_mut(block).enumerate() {
+            let order = &order;
+            scope.spawn(move || log_eval_range(circuit, order, batch, idx * block, slice));
+        }
+    });
+    Ok(result)
+}
+
+impl CompiledCircuit {
+    // log_evaluate with the nodes of each layer shared between threads, returns every node value
+    pub fn par_log_evaluate(&self, evidence: &[Option<bool>], num_threads: usize) -> Vec<f64> {
+        let num_threads = num_threads.max(1);
+        let layers = self.layers();
+        let values: Vec<AtomicU64> = (0..self.num_nodes()).map(|_| AtomicU64::new(0)).collect();
+        let barrier = Barrier::new(num_threads);
+
+        thread::scope(|scope| {
+            for thread in 0..num_threads {
+                let (layers, values, barrier) = (&layers, &values, &barrier);
+                scope.spawn(move || {
+                    for layer in layers {
+                        for &pos in layer.iter().skip(thread).step_by(num_threads) {
+                            let value = self.log_node(pos as usize, evidence, |child| {
+                                f64::from_bits(values[child].load(Ordering::Relaxed))
+                            });
+                            values[pos as usize].store(value.to_bits(), Ordering::Relaxed);
+                        }
+                        // the barrier orders the writes of a layer before the reads of the next
+                        barrier.wait();
+                    }
+                });
+            }
+        });
+
+        values
+            .into_iter()
+            .map(|value| f64::from_bits(value.into_inner()))
+            .collect()
+    }
+}
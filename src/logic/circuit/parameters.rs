@@ -0,0 +1,200 @@
+use crate::tree::{Addr, LinkingNode, Mapping};
+
+use super::{log_eval_mixed, CircuitTree, PCicruit, Weight};
+
+#[derive(Debug, Clone)]
+pub struct Fit {
+    pub max_iterations: usize,
+    // Laplace smoothing added to the expected count of every sum branch
+    pub smoothing: f64,
+    // stop when the log-likelihood improves by less than this
+    pub tolerance: f64,
+}
+
+impl Default for Fit {
+    fn default() -> Self {
+        Fit {
+            max_iterations: 100,
+            smoothing: 1.0,
+            tolerance: 1e-6,
+        }
+    }
+}
+
+// expected number of times each sum branch is followed by a sample with the given node log
+// values, returns the flow of the sample through every node
+pub(super) fn expected_counts<W: Weight>(
+    circuit: &CircuitTree<W>,
+    order: &[Addr],
+    values: &[f64],
+    counts: &mut [[f64; 2]],
+) -> Vec<f64> {
+    let mut flows = vec![0.0f64; circuit.num_nodes()];
+    if circuit.output().idx.is_none() {
+        return flows;
+    }
+    flows[circuit.output().idx.addr()] = 1.0;
+    for &idx in order.iter().rev() {
+        let flow = flows[idx.addr()];
+        if flow == 0.0 {
+            continue;
+        }
+        let operands = circuit[idx].node.operands();
+        match circuit[idx].value {
+            PCicruit::Product => {
+                flows[operands[0].addr()] += flow;
+                flows[operands[1].addr()] += flow;
+            }
+            PCicruit::Sum { left, right } => {
+                let weights = [left, right];
+                for (branch, &child) in operands.iter().enumerate() {
+                    let ratio = (weights[branch].log_prob() + values[child.addr()]
+                        - values[idx.addr()])
+                    .exp();
+                    flows[child.addr()] += flow * ratio;
+                    counts[idx.addr()][branch] += flow * ratio;
+                }
+            }
+            _ => {}
+        }
+    }
+    flows
+}
+
+// sum weights proportional to their smoothed expected counts
+pub(super) fn update_weights<W: Weight>(
+    circuit: &mut CircuitTree<W>,
+    order: &[Addr],
+    counts: &[[f64; 2]],
+    smoothing: f64,
+) {
+    for &idx in order.iter() {
+        if let PCicruit::Sum { .. } = circuit[idx].value {
+            let [left, right] = counts[idx.addr()];
+            let total = left + right + 2.0 * smoothing;
+            if total > 0.0 {
+                circuit[idx].value = PCicruit::Sum {
+                    left: W::from_prob((left + smoothing) / total),
+                    right: W::from_prob((right + smoothing) / total),
+                };
+            }
+        }
+    }
+}
+
+// one expectation-maximisation step, returns the log-likelihood before the update
+pub fn em_step<W: Weight>(
+    circuit: &mut CircuitTree<W>,
+    data: &[Vec<Option<bool>>],
+    smoothing: f64,
+) -> Result<f64, &'static str> {
+    let data: Vec<Vec<Option<f64>>> = data
+        .iter()
+        .map(|sample| sample.iter().map(|x| x.map(|x| x as u8 as f64)).collect())
+        .collect();
+    em_step_mixed(circuit, &data, smoothing)
+}
+
+// expectation-maximisation step on mixed data, the parameters of the leaves
+// are refitted along with the sum weights
+pub fn em_step_mixed<W: Weight>(
+    circuit: &mut CircuitTree<W>,
+    data: &[Vec<Option<f64>>],
+    smoothing: f64,
+) -> Result<f64, &'static str> {
+    if circuit.output().idx.is_none() {
+        return Err("Empty circuit");
+    }
+    let order = circuit.topological();
+    let mut counts = vec![[0.0f64; 2]; circuit.num_nodes()];
+    let mut observations: Vec<Vec<(f64, f64)>> = vec![Default::default(); circuit.num_nodes()];
+
+    let partition =
+        log_eval_mixed(circuit, &vec![None; circuit.num_named()])[circuit.output().idx.addr()];
+    let mut log_likelihood = 0.0;
+    for sample in data {
+        let values = log_eval_mixed(circuit, sample);
+        let log_value = values[circuit.output().idx.addr()];
+        if log_value == f64::NEG_INFINITY {
+            return Err("Sample has zero probability");
+        }
+        let flows = expected_counts(circuit, &order, &values, &mut counts);
+        log_likelihood += log_value - partition;
+
+        for &idx in order.iter() {
+            let leaf = circuit[idx].value;
+            if flows[idx.addr()] == 0.0 || !leaf.is_leaf() {
+                continue;
+            }
+            match sample[leaf.leaf_id().unwrap().addr()] {
+                Some(x) => observations[idx.addr()].push((flows[idx.addr()], x)),
+                None => {
+                    observations[idx.addr()].extend(leaf.expected_observations(flows[idx.addr()]))
+                }
+            }
+        }
+    }
+
+    update_weights(circuit, &order, &counts, smoothing);
+    for &idx in order.iter() {
+        if circuit[idx].value.is_leaf() {
+            circuit[idx].value = circuit[idx]
+                .value
+                .fit_leaf(&observations[idx.addr()], smoothing);
+        }
+    }
+    Ok(log_likelihood)
+}
+
+// maximum likelihood for complete data, on a deterministic circuit every sample follows a
+// single branch per sum so one step is exact counting
+pub fn mle_weights<W: Weight>(
+    circuit: &mut CircuitTree<W>,
+    data: &[Vec<bool>],
+    smoothing: f64,
+) -> Result<f64, &'static str> {
+    let data: Vec<Vec<Option<bool>>> = data
+        .iter()
+        .map(|sample| sample.iter().map(|&x| Some(x)).collect())
+        .collect();
+    em_step(circuit, &data, smoothing)
+}
+
+// runs the step until the log-likelihood it returns stops improving,
+// returns the log-likelihood of every iteration
+pub(super) fn iterate(
+    fit: &Fit,
+    mut step: impl FnMut() -> Result<f64, &'static str>,
+) -> Result<Vec<f64>, &'static str> {
+    let mut history: Vec<f64> = Default::default();
+    for _ in 0..fit.max_iterations {
+        let log_likelihood = step()?;
+        let converged = history
+            .last()
+            .is_some_and(|&last| log_likelihood - last < fit.tolerance);
+        history.push(log_likelihood);
+        if converged {
+            break;
+        }
+    }
+    Ok(history)
+}
+
+// expectation-maximisation on partially observed data (None for missing values),
+// returns the log-likelihood of every iteration
+pub fn fit_weights<W: Weight>(
+    circuit: &mut CircuitTree<W>,
+    data: &[Vec<Option<bool>>],
+    fit: &Fit,
+) -> Result<Vec<f64>, &'static str> {
+    iterate(fit, || em_step(circuit, data, fit.smoothing))
+}
+
+// expectation-maximisation of the sum weights and leaf parameters on mixed data
+pub fn fit_mixed<W: Weight>(
+    circuit: &mut CircuitTree<W>,
+    data: &[Vec<Option<f64>>],
+    fit: &Fit,
+) -> Result<Vec<f64>, &'static str> {
+    iterate(fit, || em_step_mixed(circuit, data, fit.smoothing))
+}
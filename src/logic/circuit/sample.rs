@@ -0,0 +1,71 @@
+use crate::{
+    random::Rng,
+    tree::{Addr, LinkingNode, Mapping},
+};
+
+use super::{log_eval_nodes, log_sum_exp, CircuitTree, PCicruit, Weight};
+
+// ancestral sampling, the circuit is expected to be smooth and decomposable
+pub fn sample<W: Weight>(circuit: &CircuitTree<W>, rng: &mut Rng, n: usize) -> Vec<Vec<bool>> {
+    let evidence = vec![None; circuit.num_named()];
+    sample_conditional(circuit, &evidence, rng, n).expect("Circuit has a null partition function")
+}
+
+pub fn sample_conditional<W: Weight>(
+    circuit: &CircuitTree<W>,
+    evidence: &[Option<bool>],
+    rng: &mut Rng,
+    n: usize,
+) -> Result<Vec<Vec<bool>>, &'static str> {
+    let values = log_eval_nodes(circuit, evidence);
+    if circuit.output().idx.is_none() || values[circuit.output().idx.addr()] == f64::NEG_INFINITY {
+        return Err("Evidence has zero probability");
+    }
+
+    Ok((0..n)
+        .map(|_| sample_one(circuit, &values, evidence, rng))
+        .collect())
+}
+
+fn sample_one<W: Weight>(
+    circuit: &CircuitTree<W>,
+    values: &[f64],
+    evidence: &[Option<bool>],
+    rng: &mut Rng,
+) -> Vec<bool> {
+    // variables outside of the circuit scope are uniform
+    let mut assignment: Vec<bool> = evidence
+        .iter()
+        .map(|value| value.unwrap_or_else(|| rng.next_bool()))
+        .collect();
+
+    let mut stack: Vec<Addr> = vec![circuit.output().idx];
+    while let Some(idx) = stack.pop() {
+        let node = &circuit[idx];
+        let operands = node.node.operands();
+        match node.value {
+            PCicruit::Variable { id, neg } => assignment[id.addr()] = !neg,
+            PCicruit::Bernoulli { id, p } => {
+                if evidence[id.addr()].is_none() {
+                    assignment[id.addr()] = rng.next_f64() < p.prob();
+                }
+            }
+            // not boolean, use sample_mixed
+            PCicruit::Indicator { .. } | PCicruit::Gaussian { .. } => {}
+            PCicruit::Product => {
+                stack.push(operands[0]);
+                stack.push(operands[1]);
+            }
+            PCicruit::Sum { left, right } => {
+                let left = left.log_prob() + values[operands[0].addr()];
+                let right = right.log_prob() + values[operands[1].addr()];
+                if rng.next_f64() < (left - log_sum_exp(left, right)).exp() {
+                    stack.push(operands[0]);
+                } else {
+                    stack.push(operands[1]);
+                }
+            }
+        }
+    }
+    assignment
+}
@@ -1,11 +1,21 @@
+use crate::bignum::{BigRational, BigUint};
 use crate::logic::circuit::propositional_to_circuit;
 use crate::logic::first_order::{FOMut, FirstOrderTree};
-use crate::logic::propositional::{PMut, PropositionalTree};
-use crate::logic::Eval;
+use crate::logic::propositional::{Cnf, Literal, PMut, PropositionalTree};
+use crate::logic::semiring::{LogProbability, MaxProduct, Tropical};
+use crate::logic::{eval_semiring, Eval};
+use crate::random::Rng;
 use crate::solver::domain::Integer;
-use crate::tree::Mapping;
+use crate::tree::{Addr, IndexedMutRef, Mapping};
 
-use super::{first_order_to_circuit, PCMut, ProbabilisticCircuitTree};
+use super::{
+    backward, cnf_to_ddnnf, convert_weights, em_step_mixed, eval_batch, eval_nodes,
+    first_order_to_circuit, fit_mixed, fit_weights, log_eval_batch, log_eval_mixed, log_likelihood,
+    log_likelihood_mixed, marginals, mle_weights, multiply, par_log_eval_batch,
+    propositional_to_ddnnf, sample, sample_conditional, sample_mixed, Batch, ChowLiuTree,
+    CircuitTree, Clustering, CompiledCircuit, Fit, Independence, LearnSpn, LogEval, LogWeight,
+    MixtureOfTrees, PCMut, PCicruit, ProbabilisticCircuitTree, Weight,
+};
 
 #[test]
 fn eval() {
@@ -16,14 +26,70 @@ fn eval() {
         )
     });
 
-    assert_eq!(pc.eval(&vec![false, false, false]), 0.0);
+    assert_eq!(pc.eval(&vec![false, false, false]), 1.0);
     assert_eq!(pc.eval(&vec![true, false, false]), 1.0);
-    assert_eq!(pc.eval(&vec![false, true, false]), 0.0);
-    assert_eq!(pc.eval(&vec![true, true, false]), 2.0);
-    assert_eq!(pc.eval(&vec![false, false, true]), 1.0);
+    assert_eq!(pc.eval(&vec![false, true, false]), 1.0);
+    assert_eq!(pc.eval(&vec![true, true, false]), 0.0);
+    assert_eq!(pc.eval(&vec![false, false, true]), 2.0);
     assert_eq!(pc.eval(&vec![true, false, true]), 2.0);
-    assert_eq!(pc.eval(&vec![false, true, true]), 1.0);
-    assert_eq!(pc.eval(&vec![true, true, true]), 3.0);
+    assert_eq!(pc.eval(&vec![false, true, true]), 2.0);
+    assert_eq!(pc.eval(&vec![true, true, true]), 1.0);
+
+    assert_eq!(pc.eval(&vec![Some(true), None, Some(false)]), 1.0);
+    assert_eq!(pc.eval(&vec![None, None, None]), 3.0);
+
+    for x in 0..8 {
+        let assignment = vec![x & 1 != 0, x & 2 != 0, x & 4 != 0];
+        let expected = pc.eval(&assignment) as f64;
+        assert!((pc.log_eval(&assignment).exp() - expected).abs() < 1e-6);
+    }
+    assert!((pc.log_eval(&[Some(true), None, Some(false)]).exp() - 1.0).abs() < 1e-6);
+    assert!((pc.log_eval(&[None, None, None]).exp() - 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn log_eval() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.25,
+            |left| left.prod(|left| left.var("A"), |right| right.not_var("B")),
+            2.0,
+            |right| right.sum(|left| left.not_var("A"), |right| right.var("C")),
+        )
+    });
+    let pc64: CircuitTree<f64> = convert_weights(&pc);
+    let pc_log: CircuitTree<LogWeight> = convert_weights(&pc);
+    assert_eq!(format!("{pc_log}"), format!("{pc}"));
+
+    for x in 0..8 {
+        let assignment = vec![x & 1 != 0, x & 2 != 0, x & 4 != 0];
+        let expected = pc.eval(&assignment) as f64;
+
+        assert!((pc.log_eval(&assignment).exp() - expected).abs() < 1e-6);
+        assert!((pc64.eval(&assignment) - expected).abs() < 1e-6);
+        assert!((pc64.log_eval(&assignment).exp() - expected).abs() < 1e-6);
+        assert!((pc_log.eval(&assignment) - expected).abs() < 1e-6);
+        assert!((pc_log.log_eval(&assignment).exp() - expected).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn log_eval_underflow() {
+    let pc = CircuitTree::<LogWeight>::build(|builder| {
+        builder.prod_n(&mut (0..400), |inner, i| {
+            let name = format!("x{i}");
+            inner.sum_w(
+                LogWeight::from_prob(0.1),
+                |left| left.var(name.as_str()),
+                LogWeight::from_prob(0.1),
+                |right| right.not_var(name.as_str()),
+            )
+        })
+    });
+    let assignment = vec![true; 400];
+
+    assert_eq!(pc.eval(&assignment), 0.0);
+    assert!((pc.log_eval(&assignment) - 400.0 * 0.1f64.ln()).abs() < 1e-6);
 }
 
 #[test]
@@ -77,6 +143,21 @@ fn compilation() {
 
     let pc = propositional_to_circuit(&input);
     assert_eq!(format!("{pc}"), "((A*(A+((B+¬C)+(A*C))))*(D*¬B))");
+
+    // the log path on compiler output, zero probabilities included
+    let mut zeros = 0;
+    for x in 0..16 {
+        let assignment: Vec<bool> = (0..4).map(|i| x & (1 << i) != 0).collect();
+        let value = pc.eval(&assignment) as f64;
+        let log_value = pc.log_eval(&assignment);
+        if value == 0.0 {
+            zeros += 1;
+            assert_eq!(log_value, f64::NEG_INFINITY);
+        } else {
+            assert!((log_value - value.ln()).abs() < 1e-6);
+        }
+    }
+    assert_eq!(zeros, 14);
 }
 
 #[test]
@@ -114,4 +195,876 @@ fn compilation_fol() {
     println!("{circuit}");
     let mar = circuit.eval(&vec![true, true, true, true, true, true, true, true, true]);
     println!("{mar}");
+
+    let mut zeros = 0;
+    for x in 0..1 << 9 {
+        let assignment: Vec<bool> = (0..9).map(|i| x & (1 << i) != 0).collect();
+        let value = circuit.eval(&assignment) as f64;
+        let log_value = circuit.log_eval(&assignment);
+        if value == 0.0 {
+            zeros += 1;
+            assert_eq!(log_value, f64::NEG_INFINITY);
+        } else {
+            assert!((log_value - value.ln()).abs() < 1e-6);
+        }
+    }
+    assert!(zeros > 0 && zeros < 1 << 9);
+}
+
+#[test]
+fn sampling() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.2,
+            |left| left.prod(|left| left.var("A"), |right| right.not_var("B")),
+            0.6,
+            |right| {
+                right.prod(
+                    |left| left.not_var("A"),
+                    |right| right.sum(|left| left.var("B"), |right| right.not_var("B")),
+                )
+            },
+        )
+    });
+
+    let samples = sample(&pc, &mut Rng::new(42), 4000);
+    assert_eq!(samples, sample(&pc, &mut Rng::new(42), 4000));
+    assert!(samples.iter().all(|x| pc.eval(x) > 0.0));
+
+    let count_a = samples.iter().filter(|x| x[0]).count() as f32 / 4000.0;
+    assert!((count_a - 0.2 / 1.4).abs() < 0.03);
+
+    let conditioned =
+        sample_conditional(&pc, &[None, Some(false)], &mut Rng::new(7), 4000).unwrap();
+    assert!(conditioned.iter().all(|x| !x[1]));
+    let count_a = conditioned.iter().filter(|x| x[0]).count() as f32 / 4000.0;
+    assert!((count_a - 0.2 / 0.8).abs() < 0.03);
+
+    assert!(sample_conditional(&pc, &[Some(true), Some(true)], &mut Rng::new(7), 1).is_err());
+}
+
+#[test]
+fn semiring() {
+    // smooth and deterministic: 0.2∙(A*¬B)+0.8∙(¬A*(0.5∙B+0.5∙¬B))
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.2,
+            |left| left.prod(|left| left.var("A"), |right| right.not_var("B")),
+            0.8,
+            |right| {
+                right.prod(
+                    |left| left.not_var("A"),
+                    |right| right.sum_w(0.5, |left| left.var("B"), 0.5, |right| right.not_var("B")),
+                )
+            },
+        )
+    });
+
+    assert!(eval_semiring::<bool, _, _>(&pc, |_, _| true));
+    assert_eq!(eval_semiring::<u128, _, _>(&pc, |_, _| 1), 3);
+    assert!((eval_semiring::<f64, _, _>(&pc, |_, _| 1.0) - 1.0).abs() < 1e-6);
+
+    let evidence = [Some(false), None];
+    let indicator = |id: Addr, neg: bool| match evidence[id.addr()] {
+        Some(value) if value == neg => 0.0,
+        _ => 1.0,
+    };
+    let marginal = eval_semiring(&pc, indicator);
+    assert!((marginal - pc.eval(&evidence.to_vec()) as f64).abs() < 1e-6);
+    assert!((pc.log_eval(&evidence).exp() - marginal).abs() < 1e-6);
+    assert!(pc.log_eval(&[None, None]).abs() < 1e-6);
+    let log_marginal = eval_semiring(&pc, |id, neg| LogProbability(indicator(id, neg).ln()));
+    assert!((log_marginal.0 - marginal.ln()).abs() < 1e-6);
+
+    let mpe = eval_semiring(&pc, |_, _| MaxProduct(1.0));
+    assert!((mpe.0 - 0.4).abs() < 1e-6);
+    let cost = eval_semiring(&pc, |_, _| Tropical(0.0));
+    assert!((cost.0 + 0.4f64.ln()).abs() < 1e-6);
+}
+
+#[test]
+fn exact_counts() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.prod_n(&mut (0..140), |inner, i| {
+            let name = format!("x{i}");
+            inner.sum_w(
+                0.25,
+                |left| left.var(name.as_str()),
+                0.75,
+                |right| right.not_var(name.as_str()),
+            )
+        })
+    });
+
+    assert_eq!(
+        format!("{}", eval_semiring(&pc, |_, _| BigUint::from(1u64))),
+        "1393796574908163946345982392040522594123776"
+    );
+    let one = BigRational::from(BigUint::from(1u64));
+    assert_eq!(eval_semiring(&pc, |_, _| one.clone()), one);
+}
+
+#[test]
+fn parameter_learning() {
+    // deterministic: A selects between two distributions over B
+    let mut pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum(
+            |left| {
+                left.prod(
+                    |left| left.var("A"),
+                    |right| right.sum(|left| left.var("B"), |right| right.not_var("B")),
+                )
+            },
+            |right| {
+                right.prod(
+                    |left| left.not_var("A"),
+                    |right| right.sum(|left| left.var("B"), |right| right.not_var("B")),
+                )
+            },
+        )
+    });
+    let data = vec![
+        vec![true, true],
+        vec![true, true],
+        vec![true, false],
+        vec![false, false],
+    ];
+
+    let weights = |pc: &ProbabilisticCircuitTree| {
+        let mut weights: Vec<(f32, f32)> = pc
+            .topological()
+            .into_iter()
+            .filter_map(|idx| match pc[idx].value {
+                PCicruit::Sum { left, right } => Some((left, right)),
+                _ => None,
+            })
+            .collect();
+        weights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        weights
+    };
+
+    mle_weights(&mut pc, &data, 0.0).unwrap();
+    assert_eq!(
+        weights(&pc),
+        vec![(0.0, 1.0), (0.6666667, 0.33333334), (0.75, 0.25)]
+    );
+
+    mle_weights(&mut pc, &data, 1.0).unwrap();
+    assert_eq!(
+        weights(&pc),
+        vec![(0.33333334, 0.6666667), (0.6, 0.4), (0.6666667, 0.33333334)]
+    );
+
+    let expected = [0.75f64.ln() + (2.0f64 / 3.0).ln(); 2]
+        .into_iter()
+        .chain([0.75f64.ln() + (1.0f64 / 3.0).ln(), 0.25f64.ln()])
+        .sum::<f64>();
+    mle_weights(&mut pc, &data, 0.0).unwrap();
+    assert!((mle_weights(&mut pc, &data, 0.0).unwrap() - expected).abs() < 1e-5);
+
+    // missing values, without smoothing the likelihood never decreases
+    let partial = vec![
+        vec![Some(true), None],
+        vec![None, Some(true)],
+        vec![Some(false), Some(false)],
+        vec![None, None],
+        vec![Some(true), Some(true)],
+    ];
+    let fit = Fit {
+        smoothing: 0.0,
+        ..Default::default()
+    };
+    let history = fit_weights(&mut pc, &partial, &fit).unwrap();
+    assert!(history.len() > 1);
+    assert!(history.windows(2).all(|x| x[1] >= x[0] - 1e-9));
+
+    let mut empty = ProbabilisticCircuitTree::default();
+    assert_eq!(
+        fit_weights(&mut empty, &partial, &fit),
+        Err("Empty circuit")
+    );
+    assert_eq!(
+        mle_weights(&mut empty, &[vec![true]], 1.0),
+        Err("Empty circuit")
+    );
+}
+
+#[test]
+fn gradient() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.3,
+            |left| {
+                left.prod(
+                    |left| left.var("A"),
+                    |right| right.sum_w(0.9, |left| left.var("B"), 0.1, |right| right.not_var("B")),
+                )
+            },
+            0.7,
+            |right| {
+                right.prod(
+                    |left| left.not_var("A"),
+                    |right| right.sum_w(0.2, |left| left.var("B"), 0.8, |right| right.not_var("B")),
+                )
+            },
+        )
+    });
+    let evidence = vec![None, Some(true)];
+    let values = eval_nodes(&pc, &evidence);
+    let gradient = backward(&pc, &values);
+    let output = pc.output().idx;
+
+    // finite differences on every sum weight
+    for idx in pc.topological() {
+        if let PCicruit::Sum { left, right } = pc[idx].value {
+            let mut shifted = pc.clone();
+            shifted[idx].value = PCicruit::Sum {
+                left: left + 1e-2,
+                right,
+            };
+            let difference = (shifted.eval(&evidence) - values[output.addr()]) / 1e-2;
+            assert!((difference as f64 - gradient.weights[idx.addr()][0]).abs() < 1e-3);
+        }
+    }
+    assert_eq!(gradient.nodes[output.addr()], 1.0);
+
+    let marginals = marginals(&pc, &evidence);
+    assert!((marginals[0][1] - pc.eval(&vec![Some(true), Some(true)]) as f64).abs() < 1e-6);
+    assert!((marginals[0][0] - pc.eval(&vec![Some(false), Some(true)]) as f64).abs() < 1e-6);
+    assert!((marginals[1][1] - pc.eval(&vec![None, Some(true)]) as f64).abs() < 1e-6);
+    assert!((marginals[1][0] - pc.eval(&vec![None, Some(false)]) as f64).abs() < 1e-6);
+}
+
+#[test]
+fn batch() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.25,
+            |left| left.prod(|left| left.var("A"), |right| right.not_var("B")),
+            2.0,
+            |right| right.sum(|left| left.not_var("A"), |right| right.var("C")),
+        )
+    });
+    let mut rng = Rng::new(3);
+    let rows: Vec<Vec<bool>> = (0..1000)
+        .map(|_| (0..3).map(|_| rng.next_bool()).collect())
+        .collect();
+    let batch = Batch::from_rows(3, &rows);
+    assert_eq!(batch.row(17), rows[17]);
+
+    let values = eval_batch(&pc, &batch).unwrap();
+    let log_values = log_eval_batch(&pc, &batch).unwrap();
+    for (i, row) in rows.iter().enumerate() {
+        assert!((values[i] - pc.eval(row) as f64).abs() < 1e-6);
+        assert_eq!(log_values[i], pc.log_eval(row));
+    }
+
+    // every assignment has a non-zero probability
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.25,
+            |left| {
+                left.prod_n(&mut ["A", "B", "C"].into_iter(), |inner, name| {
+                    inner.sum_w(0.4, |left| left.var(name), 0.6, |right| right.not_var(name))
+                })
+            },
+            2.0,
+            |right| {
+                right.prod_n(&mut ["A", "B", "C"].into_iter(), |inner, name| {
+                    inner.sum_w(0.9, |left| left.var(name), 0.1, |right| right.not_var(name))
+                })
+            },
+        )
+    });
+    let partition = pc.eval(&vec![None; 3]) as f64;
+    let expected: f64 = rows
+        .iter()
+        .map(|row| (pc.eval(row) as f64 / partition).ln())
+        .sum();
+    assert!((log_likelihood(&pc, &batch).unwrap() - expected).abs() < 1e-3);
+    assert_eq!(
+        log_likelihood(&ProbabilisticCircuitTree::default(), &batch),
+        Ok(f64::NEG_INFINITY)
+    );
+}
+
+#[test]
+fn compiled() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum_w(
+            0.3,
+            |left| {
+                left.prod(
+                    |left| left.var("A"),
+                    |right| right.sum_w(0.9, |left| left.var("B"), 0.1, |right| right.not_var("B")),
+                )
+            },
+            0.7,
+            |right| {
+                right.prod(
+                    |left| left.not_var("A"),
+                    |right| right.sum_w(0.2, |left| left.var("C"), 0.8, |right| right.not_var("B")),
+                )
+            },
+        )
+    });
+    let compiled = CompiledCircuit::new(&pc).unwrap();
+    assert_eq!(compiled.num_nodes(), pc.num_nodes());
+    assert_eq!(compiled.addrs.last(), Some(&pc.output().idx));
+
+    let mut values = Vec::new();
+    for x in 0..27 {
+        let evidence: Vec<Option<bool>> = (0..3)
+            .map(|i| [None, Some(false), Some(true)][(x / 3usize.pow(i)) % 3])
+            .collect();
+        let expected = pc.eval(&evidence) as f64;
+        assert!((compiled.evaluate(&evidence, &mut values) - expected).abs() < 1e-6);
+        assert!((compiled.log_evaluate(&evidence, &mut values).exp() - expected).abs() < 1e-6);
+
+        let reference = backward(&pc, &eval_nodes(&pc, &evidence));
+        compiled.evaluate(&evidence, &mut values);
+        let gradient = compiled.gradient(&values);
+        for (pos, idx) in compiled.addrs.iter().enumerate() {
+            assert!((gradient.nodes[pos] - reference.nodes[idx.addr()]).abs() < 1e-6);
+            for branch in 0..2 {
+                assert!(
+                    (gradient.weights[pos][branch] - reference.weights[idx.addr()][branch]).abs()
+                        < 1e-6
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn parallel() {
+    let input = FirstOrderTree::build(|builder| {
+        builder.every("x", |inner| {
+            inner.every("y", |inner| {
+                inner.or(
+                    |left| left.not(|inner| inner.pred("Edge", &["x", "y"])),
+                    |right| right.pred("Edge", &["y", "x"]),
+                )
+            })
+        })
+    });
+    let mut pc = first_order_to_circuit(
+        &input,
+        &[Integer {
+            vars: vec![
+                input.get_id(&"x".to_string()),
+                input.get_id(&"y".to_string()),
+            ],
+            card: 6,
+        }],
+    );
+    let mut rng = Rng::new(11);
+    for idx in pc.topological() {
+        if let PCicruit::Sum { .. } = pc[idx].value {
+            pc[idx].value = PCicruit::Sum {
+                left: rng.next_f64() as f32,
+                right: rng.next_f64() as f32,
+            };
+        }
+    }
+
+    let rows: Vec<Vec<bool>> = (0..1000)
+        .map(|_| (0..pc.num_named()).map(|_| rng.next_bool()).collect())
+        .collect();
+    let batch = Batch::from_rows(pc.num_named(), &rows);
+    let serial = log_eval_batch(&pc, &batch).unwrap();
+    for num_threads in [1, 3, 8] {
+        assert_eq!(
+            par_log_eval_batch(&pc, &batch, num_threads).unwrap(),
+            serial
+        );
+    }
+
+    let compiled = CompiledCircuit::new(&pc).unwrap();
+    let evidence: Vec<Option<bool>> = (0..pc.num_named())
+        .map(|i| [None, Some(false), Some(true)][i % 3])
+        .collect();
+    let mut values = Vec::new();
+    compiled.log_evaluate(&evidence, &mut values);
+    for num_threads in [1, 4] {
+        assert_eq!(compiled.par_log_evaluate(&evidence, num_threads), values);
+    }
+}
+
+#[test]
+fn ddnnf() {
+    let mut rng = Rng::new(5);
+    for num_clauses in [0, 5, 15, 30, 45] {
+        let mut cnf = Cnf::new(8);
+        for _ in 0..num_clauses {
+            let clause: Vec<Literal> = (0..3)
+                .map(|_| Literal::new(Addr::new(rng.below(8)), rng.next_bool()))
+                .collect();
+            cnf.add_clause(&clause);
+        }
+
+        let Ok(pc) = cnf_to_ddnnf(&cnf) else {
+            assert!((0..256)
+                .all(|x| !cnf.eval(&(0..8).map(|i| x & (1 << i) != 0).collect::<Vec<bool>>())));
+            continue;
+        };
+        let mut models = 0u128;
+        for x in 0..256 {
+            let assignment: Vec<bool> = (0..8).map(|i| x & (1 << i) != 0).collect();
+            let expected = cnf.eval(&assignment);
+            models += expected as u128;
+            assert_eq!(pc.eval(&assignment), if expected { 1.0 } else { 0.0 });
+        }
+        assert_eq!(eval_semiring(&pc, |_, _| 1u128), models);
+        assert_eq!(pc.eval(&vec![None; 8]), models as f32);
+    }
+
+    let input = PropositionalTree::build(|builder| {
+        builder.and(
+            |left| left.or(|left| left.var("A"), |right| right.var("B")),
+            |right| {
+                right.or(
+                    |left| left.not(|inner| inner.var("A")),
+                    |right| right.var("C"),
+                )
+            },
+        )
+    });
+    let pc = propositional_to_ddnnf(&input).unwrap();
+    assert_eq!(pc.named, input.named);
+    assert_eq!(
+        eval_semiring(&pc, |_, _| BigUint::from(1u64)),
+        BigUint::from(4u64)
+    );
+
+    // independent components are compiled in the same order every time
+    let mut pairs = Cnf::new(8);
+    for i in 0..4 {
+        pairs.add_clause(&[
+            Literal::new(Addr::new(2 * i), false),
+            Literal::new(Addr::new(2 * i + 1), false),
+        ]);
+    }
+    let pc = cnf_to_ddnnf(&pairs).unwrap();
+    for _ in 0..8 {
+        assert_eq!(cnf_to_ddnnf(&pairs).as_ref(), Ok(&pc));
+    }
+
+    let mut unsat = Cnf::new(2);
+    unsat.add_clause(&[Literal::from_dimacs(1)]);
+    unsat.add_clause(&[Literal::from_dimacs(-1)]);
+    assert_eq!(cnf_to_ddnnf(&unsat), Err("Formula is unsatisfiable"));
+    assert_eq!(cnf_to_ddnnf(&Cnf::new(0)), Err("Formula has no variables"));
+    unsat = Cnf::new(0);
+    unsat.add_clause(&[]);
+    assert_eq!(cnf_to_ddnnf(&unsat), Err("Formula is unsatisfiable"));
+}
+
+#[test]
+fn leaves() {
+    let pc = ProbabilisticCircuitTree::build(|builder| {
+        builder.prod(
+            |left| left.bernoulli("A", 0.3),
+            |right| right.sum_w(0.25, |left| left.var("B"), 0.75, |right| right.not_var("B")),
+        )
+    });
+    let expected = 0.3f32 * 0.75;
+    assert!((pc.eval(&vec![true, false]) - expected).abs() < 1e-6);
+    assert!((pc.eval(&vec![None, None]) - 1.0).abs() < 1e-6);
+    let log_value = pc.log_eval(&[Some(true), Some(false)]);
+    assert!((log_value - (expected as f64).ln()).abs() < 1e-6);
+    let compiled = CompiledCircuit::new(&pc).unwrap();
+    let mut values = Vec::new();
+    let value = compiled.evaluate(&[Some(true), Some(false)], &mut values);
+    assert!((value - expected as f64).abs() < 1e-6);
+    let batch = Batch::from_rows(2, &[vec![true, false], vec![false, false]]);
+    let batch_values = eval_batch(&pc, &batch).unwrap();
+    assert!((batch_values[1] - 0.7 * 0.75).abs() < 1e-6);
+    let marginal = marginals(&pc, &[None, None]);
+    assert!((marginal[0][1] - 0.3).abs() < 1e-6);
+    assert_eq!(
+        format!("{pc}"),
+        "(B(A; 0.300)*(0.250\u{2219}B+0.750\u{2219}\u{00AC}B))"
+    );
+
+    let mut rng = Rng::new(47);
+    let samples = sample(&pc, &mut rng, 4000);
+    let ones = samples.iter().filter(|x| x[0]).count() as f64 / 4000.0;
+    assert!((ones - 0.3).abs() < 0.03);
+
+    // mixture of two clusters over a real X, a category C and a boolean D
+    fn component(
+        builder: &mut IndexedMutRef<ProbabilisticCircuitTree>,
+        mean: f64,
+        probs: &[f64],
+        p: f32,
+    ) -> Addr {
+        builder.prod(
+            |left| left.gaussian("X", mean, 1.0 + mean),
+            |right| {
+                right.prod(
+                    |left| left.categorical("C", probs),
+                    |right| right.bernoulli("D", p),
+                )
+            },
+        )
+    }
+    let mixture = |weight: f32| {
+        ProbabilisticCircuitTree::build(|builder| {
+            builder.sum_w(
+                weight,
+                |left| component(left, 0.0, &[0.7, 0.2, 0.1], 0.9),
+                1.0 - weight,
+                |right| component(right, 4.0, &[0.1, 0.1, 0.8], 0.2),
+            )
+        })
+    };
+    let pc = mixture(0.4);
+    let point = [Some(1.0), Some(2.0), Some(0.0)];
+    let density = |x: f64, mean: f64, variance: f64| {
+        (-(x - mean).powi(2) / (2.0 * variance)).exp()
+            / (2.0 * std::f64::consts::PI * variance).sqrt()
+    };
+    let expected =
+        0.4 * density(1.0, 0.0, 1.0) * 0.1 * 0.1 + 0.6 * density(1.0, 4.0, 5.0) * 0.8 * 0.8;
+    let log_value = log_likelihood_mixed(&pc, &point);
+    assert!((log_value - expected.ln()).abs() < 1e-5, "{log_value}");
+    let output = pc.output().idx.addr();
+    assert!(log_eval_mixed(&pc, &[None, None, None])[output].abs() < 1e-6);
+    let marginal = 0.4 * 0.1 + 0.6 * 0.8;
+    let log_value = log_eval_mixed(&pc, &[None, Some(2.0), None])[output];
+    assert!((log_value - f64::ln(marginal)).abs() < 1e-6);
+
+    // boolean evaluation only handles variables and Bernoulli leaves
+    let message = "Circuit has non-boolean leaves, use log_eval_mixed";
+    assert_eq!(CompiledCircuit::new(&pc).err(), Some(message));
+    let batch = Batch::from_rows(3, &[vec![false, true, true]]);
+    assert_eq!(eval_batch(&pc, &batch).err(), Some(message));
+    assert_eq!(log_likelihood(&pc, &batch).err(), Some(message));
+
+    let data = sample_mixed(&pc, &[None, None, None], &mut rng, 3000).unwrap();
+    let mean = data.iter().map(|x| x[0]).sum::<f64>() / 3000.0;
+    assert!((mean - 0.6 * 4.0).abs() < 0.2, "{mean}");
+    let twos = data.iter().filter(|x| x[1] == 2.0).count() as f64 / 3000.0;
+    assert!((twos - marginal).abs() < 0.03);
+    let conditional = sample_mixed(&pc, &[Some(-3.0), None, None], &mut rng, 200).unwrap();
+    assert!(conditional.iter().all(|x| x[0] == -3.0));
+
+    // maximum likelihood of a single component is exact in one step
+    let single =
+        ProbabilisticCircuitTree::build(|builder| component(builder, 1.0, &[1.0, 1.0, 1.0], 0.5));
+    let mut fitted = single.clone();
+    let observed: Vec<Vec<Option<f64>>> = data
+        .iter()
+        .map(|x| x.iter().map(|&value| Some(value)).collect())
+        .collect();
+    em_step_mixed(&mut fitted, &observed, 0.0).unwrap();
+    let variance = data.iter().map(|x| (x[0] - mean).powi(2)).sum::<f64>() / 3000.0;
+    let ones = data.iter().filter(|x| x[2] == 1.0).count() as f64 / 3000.0;
+    for idx in fitted.topological() {
+        match fitted[idx].value {
+            PCicruit::Gaussian {
+                mean: m,
+                variance: v,
+                ..
+            } => {
+                assert!((m - mean).abs() < 1e-9 && (v - variance).abs() < 1e-9);
+            }
+            PCicruit::Bernoulli { p, .. } => assert!((p.prob() - ones).abs() < 1e-6),
+            _ => {}
+        }
+    }
+    let log_value = log_eval_mixed(&fitted, &[None, Some(2.0), None])[fitted.output().idx.addr()];
+    assert!((log_value.exp() - twos).abs() < 1e-5);
+
+    // expectation-maximisation from a perturbed mixture, with missing values
+    let mut partial = observed.clone();
+    for (i, sample) in partial.iter_mut().enumerate() {
+        sample[i % 3] = None;
+    }
+    let mut fitted = mixture(0.5);
+    let history = fit_mixed(
+        &mut fitted,
+        &partial,
+        &Fit {
+            smoothing: 0.0,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(history.windows(2).all(|x| x[1] >= x[0] - 1e-6));
+    let reference: f64 = partial.iter().map(|x| log_likelihood_mixed(&pc, x)).sum();
+    assert!(*history.last().unwrap() > reference - 30.0);
+}
+
+#[test]
+#[should_panic(expected = "Categorical leaf without values")]
+fn categorical_without_values() {
+    ProbabilisticCircuitTree::build(|builder| builder.categorical("C", &[]));
+}
+
+#[test]
+#[should_panic(expected = "Categorical probabilities must have a positive total")]
+fn categorical_without_mass() {
+    ProbabilisticCircuitTree::build(|builder| builder.categorical("C", &[0.0, 0.0]));
+}
+
+#[test]
+fn learn_spn() {
+    // two clusters over the first five variables, the last one independent
+    let mut rng = Rng::new(48);
+    let data: Vec<Vec<bool>> = (0..1000)
+        .map(|_| {
+            let p = if rng.next_bool() { 0.9 } else { 0.1 };
+            let mut sample: Vec<bool> = (0..5).map(|_| rng.next_f64() < p).collect();
+            sample.push(rng.next_f64() < 0.3);
+            sample
+        })
+        .collect();
+    let batch = Batch::from_rows(6, &data);
+
+    let factorised = LearnSpn {
+        min_samples: usize::MAX,
+        ..Default::default()
+    }
+    .learn(&data, &mut rng)
+    .unwrap();
+    let baseline = log_likelihood(&factorised, &batch).unwrap();
+
+    for learner in [
+        LearnSpn::default(),
+        LearnSpn {
+            independence: Independence::MutualInformation { threshold: 0.01 },
+            clustering: Clustering::Em,
+            ..Default::default()
+        },
+    ] {
+        let pc = learner.learn(&data, &mut rng).unwrap();
+        assert_eq!(pc.num_named(), 6);
+        assert!(pc.log_eval(&[None; 6]).abs() < 1e-5);
+        assert_eq!(pc[pc.output().idx].value, PCicruit::Product);
+        let value = log_likelihood(&pc, &batch).unwrap();
+        assert!(value > baseline + 500.0, "{value} {baseline}");
+    }
+
+    assert!(LearnSpn::default().learn(&[], &mut rng).is_err());
+    assert_eq!(
+        LearnSpn::default().learn(&[vec![]], &mut rng),
+        Err("No variables")
+    );
+    assert!(LearnSpn::default()
+        .learn(&[vec![true], vec![true, false]], &mut rng)
+        .is_err());
+}
+
+#[test]
+fn chow_liu() {
+    // markov chain where every variable copies the previous one with probability 0.9,
+    // the mixture also has chains alternating their values
+    let mut rng = Rng::new(49);
+    let mut chain = |alternating: bool| -> Vec<bool> {
+        let mut sample = vec![rng.next_f64() < 0.6];
+        for i in 1..6 {
+            let copy = rng.next_f64() < 0.9;
+            sample.push(sample[i - 1] ^ !copy ^ alternating);
+        }
+        sample
+    };
+    let data: Vec<Vec<bool>> = (0..2000).map(|_| chain(false)).collect();
+    let mixed: Vec<Vec<bool>> = (0..2000).map(|i| chain(i % 2 == 0)).collect();
+
+    let tree = ChowLiuTree::fit(&data, 1.0).unwrap();
+    assert_eq!(tree.parents[0], None);
+    for v in 1..6 {
+        assert_eq!(tree.parents[v], Some(v - 1));
+    }
+    assert!((tree.probs[0][0] - 0.6).abs() < 0.03);
+    assert!((tree.probs[3][1] - 0.9).abs() < 0.03);
+
+    let pc = tree.to_circuit();
+    assert!(pc.log_eval(&[None; 6]).abs() < 1e-5);
+    for sample in data.iter().take(20) {
+        assert!((pc.log_eval(sample) - tree.log_prob(sample)).abs() < 1e-4);
+    }
+    // deterministic, so complete data has exact maximum likelihood weights
+    let mut fitted = pc.clone();
+    mle_weights(&mut fitted, &data, 1.0).unwrap();
+    let batch = Batch::from_rows(6, &data);
+    assert!(
+        (log_likelihood(&fitted, &batch).unwrap() - log_likelihood(&pc, &batch).unwrap()).abs()
+            < 1.0
+    );
+
+    let single = ChowLiuTree::fit(&mixed, 1.0).unwrap();
+    let single: f64 = mixed.iter().map(|x| single.log_prob(x)).sum();
+    let mut mixture = MixtureOfTrees::new(&mixed, 2, 1.0, &mut rng).unwrap();
+    let history = mixture.fit(&mixed, &Fit::default()).unwrap();
+    assert!(history.windows(2).all(|x| x[1] >= x[0] - 1.0));
+    let value: f64 = mixed.iter().map(|x| mixture.log_prob(x)).sum();
+    assert!(value > single + 300.0, "{value} {single}");
+
+    let pc = mixture.to_circuit();
+    assert!(pc.log_eval(&[None; 6]).abs() < 1e-5);
+    let batch = Batch::from_rows(6, &mixed);
+    assert!((log_likelihood(&pc, &batch).unwrap() - value).abs() < 1e-2 * mixed.len() as f64);
+
+    assert!(ChowLiuTree::fit(&[], 1.0).is_err());
+    assert!(MixtureOfTrees::new(&data, 0, 1.0, &mut rng).is_err());
+    assert!(mixture.em_step(&[vec![true]], 1.0).is_err());
+}
+
+#[test]
+fn multiplication() {
+    let assignments = |n: usize| {
+        (0..1usize << n).map(move |i| (0..n).map(|j| i & (1 << j) != 0).collect::<Vec<bool>>())
+    };
+
+    // learned distribution over A and B times the constraint A or B, with the variables and
+    // the factors of the products in another order
+    let distribution = ProbabilisticCircuitTree::build(|builder| {
+        builder.prod(
+            |left| left.bernoulli("A", 0.3),
+            |right| right.sum_w(0.6, |left| left.var("B"), 0.4, |right| right.not_var("B")),
+        )
+    });
+    let constraint = ProbabilisticCircuitTree::build(|builder| {
+        builder.sum(
+            |left| {
+                left.prod(
+                    |left| left.var("B"),
+                    |right| right.sum(|l| l.var("A"), |r| r.not_var("A")),
+                )
+            },
+            |right| right.prod(|left| left.not_var("B"), |right| right.var("A")),
+        )
+    });
+    assert_eq!(constraint.get_id(&"B".to_string()), Addr::new(0));
+    let pc = multiply(&distribution, &constraint).unwrap();
+    assert_eq!(pc.get_id(&"A".to_string()), Addr::new(0));
+    for assignment in assignments(2) {
+        let swapped = vec![assignment[1], assignment[0]];
+        let expected = distribution.eval(&assignment) * constraint.eval(&swapped);
+        assert!((pc.eval(&assignment) - expected).abs() < 1e-6);
+    }
+    assert!((pc.eval(&vec![None, None]) - (1.0 - 0.7 * 0.4)).abs() < 1e-6);
+
+    // the constant of the Bernoulli goes into the sum next to it at the output
+    let restricted = ProbabilisticCircuitTree::build(|builder| {
+        builder.prod(
+            |left| left.var("A"),
+            |right| right.sum(|left| left.var("B"), |right| right.not_var("B")),
+        )
+    });
+    let pc = multiply(&distribution, &restricted).unwrap();
+    assert!((pc.eval(&vec![None, None]) - 0.3).abs() < 1e-6);
+
+    // Chow-Liu tree restricted by a circuit over the same tree
+    let tree = ChowLiuTree {
+        parents: vec![None, Some(0), Some(1), Some(1)],
+        probs: vec![[0.4, 0.4], [0.2, 0.7], [0.5, 0.9], [0.1, 0.3]],
+    };
+    let parity = ChowLiuTree {
+        parents: tree.parents.clone(),
+        probs: vec![[0.5, 0.5], [1.0, 0.0], [0.0, 1.0], [1.0, 0.0]],
+    };
+    let (tree, parity) = (tree.to_circuit(), parity.to_circuit());
+    let pc = multiply(&tree, &parity).unwrap();
+    for assignment in assignments(4) {
+        let expected = tree.eval(&assignment) * parity.eval(&assignment);
+        assert!((pc.eval(&assignment) - expected).abs() < 1e-6);
+    }
+
+    // learned distribution times a first-order constraint, universally quantified literals
+    // give a smooth product
+    let mut rng = Rng::new(17);
+    let data: Vec<Vec<bool>> = (0..200)
+        .map(|_| {
+            let value = rng.next_bool();
+            (0..3).map(|_| value ^ (rng.below(10) == 0)).collect()
+        })
+        .collect();
+    let learned = LearnSpn {
+        min_samples: 150,
+        ..Default::default()
+    }
+    .learn(&data, &mut rng)
+    .unwrap();
+    let every =
+        FirstOrderTree::build(|builder| builder.every("x", |inner| inner.pred("P", &["x"])));
+    let domains = [Integer {
+        vars: vec![every.get_id(&"x".to_string())],
+        card: 3,
+    }];
+    let constraint = first_order_to_circuit(&every, &domains);
+    assert!(matches!(
+        learned[learned.output().idx].value,
+        PCicruit::Sum { .. }
+    ));
+    let pc = multiply(&learned, &constraint).unwrap();
+    for assignment in assignments(3) {
+        let expected = learned.eval(&assignment) * constraint.eval(&assignment);
+        assert!((pc.eval(&assignment) - expected).abs() < 1e-6);
+    }
+    let exists =
+        FirstOrderTree::build(|builder| builder.exist("x", |inner| inner.pred("P", &["x"])));
+    let domains = [Integer {
+        vars: vec![exists.get_id(&"x".to_string())],
+        card: 3,
+    }];
+    assert_eq!(
+        multiply(&learned, &first_order_to_circuit(&exists, &domains)),
+        Err("Circuits are not compatible")
+    );
+
+    // product of Gaussians, a leaf cannot hold the constant so the result is unnormalised
+    let gaussian = |mean: f64, variance: f64| {
+        ProbabilisticCircuitTree::build(|builder| builder.gaussian("X", mean, variance))
+    };
+    let (first, second) = (gaussian(1.0, 2.0), gaussian(-1.0, 0.5));
+    let pc = multiply(&first, &second).unwrap();
+    let value = |pc: &ProbabilisticCircuitTree, x: f64| {
+        log_eval_mixed(pc, &[Some(x)])[pc.output().idx.addr()]
+    };
+    let ratio = |pc: &ProbabilisticCircuitTree| value(pc, 0.3) - value(pc, -0.8);
+    assert!((ratio(&pc) - ratio(&first) - ratio(&second)).abs() < 1e-6);
+
+    // the scopes of the products differ
+    let split = |first: &str, second: &str, third: &str| {
+        ProbabilisticCircuitTree::build(|builder| {
+            builder.prod(
+                |left| {
+                    left.sum(
+                        |left| left.prod(|left| left.var(first), |right| right.var(second)),
+                        |right| {
+                            right.prod(|left| left.not_var(first), |right| right.not_var(second))
+                        },
+                    )
+                },
+                |right| right.var(third),
+            )
+        })
+    };
+    let first = split("A", "B", "C");
+    let mut second = ProbabilisticCircuitTree::default();
+    second.copy_named(&first);
+    second.builder(|builder| {
+        builder.prod(
+            |left| {
+                left.sum(
+                    |left| left.prod(|left| left.var("A"), |right| right.var("C")),
+                    |right| right.prod(|left| left.not_var("A"), |right| right.not_var("C")),
+                )
+            },
+            |right| right.var("B"),
+        )
+    });
+    assert!(multiply(&first, &split("A", "B", "C")).is_ok());
+    assert_eq!(
+        multiply(&first, &second),
+        Err("Circuits are not compatible")
+    );
+    assert_eq!(
+        multiply(&first, &split("A", "B", "D")),
+        Err("Variables do not match")
+    );
 }
@@ -0,0 +1,108 @@
+use std::fmt::Debug;
+
+use num_traits::Float;
+
+pub trait Weight: Copy + Debug + PartialEq {
+    type Value: Float + Debug;
+
+    const ONE: Self;
+
+    fn from_prob(p: f64) -> Self;
+    fn from_log_prob(p: f64) -> Self;
+    fn prob(self) -> f64;
+    fn log_prob(self) -> f64;
+    fn value(self) -> Self::Value;
+}
+
+impl Weight for f32 {
+    type Value = f32;
+
+    const ONE: Self = 1.0;
+
+    fn from_prob(p: f64) -> Self {
+        p as f32
+    }
+
+    fn from_log_prob(p: f64) -> Self {
+        p.exp() as f32
+    }
+
+    fn prob(self) -> f64 {
+        self as f64
+    }
+
+    fn log_prob(self) -> f64 {
+        (self as f64).ln()
+    }
+
+    fn value(self) -> Self::Value {
+        self
+    }
+}
+
+impl Weight for f64 {
+    type Value = f64;
+
+    const ONE: Self = 1.0;
+
+    fn from_prob(p: f64) -> Self {
+        p
+    }
+
+    fn from_log_prob(p: f64) -> Self {
+        p.exp()
+    }
+
+    fn prob(self) -> f64 {
+        self
+    }
+
+    fn log_prob(self) -> f64 {
+        self.ln()
+    }
+
+    fn value(self) -> Self::Value {
+        self
+    }
+}
+
+// weight stored as its natural logarithm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LogWeight(pub f64);
+
+impl Weight for LogWeight {
+    type Value = f64;
+
+    const ONE: Self = LogWeight(0.0);
+
+    fn from_prob(p: f64) -> Self {
+        LogWeight(p.ln())
+    }
+
+    fn from_log_prob(p: f64) -> Self {
+        LogWeight(p)
+    }
+
+    fn prob(self) -> f64 {
+        self.0.exp()
+    }
+
+    fn log_prob(self) -> f64 {
+        self.0
+    }
+
+    fn value(self) -> Self::Value {
+        self.0.exp()
+    }
+}
+
+// ln(exp(a) + exp(b)) without overflow, -inf is the neutral element
+#[inline]
+pub fn log_sum_exp(a: f64, b: f64) -> f64 {
+    let max = a.max(b);
+    if max == f64::NEG_INFINITY {
+        f64::NEG_INFINITY
+    } else {
+        max + ((a - max).exp() + (b - max).exp()).ln()
+    }
+}
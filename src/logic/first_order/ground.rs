@@ -31,17 +31,23 @@ impl Grounded {
         self.grounded[addr]
     }
 
-    fn format(&self, radical: &String, idx: usize) -> String {
+    // argument values of the idx-th grounded atom
+    pub fn arguments(&self, idx: usize) -> Vec<usize> {
         let mut vars: Vec<usize> = Default::default();
         let mut current_idx = idx;
         for domain in self.domains.iter().rev() {
             vars.push(current_idx % domain.card);
             current_idx = current_idx / domain.card;
         }
+        vars.reverse();
+        vars
+    }
+
+    fn format(&self, radical: &String, idx: usize) -> String {
         format!(
             "{radical}({})",
-            vars.iter()
-                .rev()
+            self.arguments(idx)
+                .iter()
                 .map(|x| x.to_string())
                 .collect::<Vec<String>>()
                 .join(", ")
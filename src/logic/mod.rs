@@ -1,8 +1,13 @@
+pub mod bdd;
 pub mod circuit;
 #[macro_use]
 pub mod expr;
 pub mod first_order;
+pub mod order;
 pub mod propositional;
+pub mod sdd;
 pub mod semantic;
+pub mod semiring;
 
 pub use semantic::{Eval, Semantic, SemanticNode};
+pub use semiring::{eval_semiring, Semiring, SemiringEval};
@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::{
+    logic::{
+        first_order::ground::Grounded,
+        propositional::{Cnf, PLogic, PropositionalTree},
+        sdd::Vtree,
+    },
+    tree::{Addr, LinkingNode, Mapping},
+};
+
+#[cfg(test)]
+mod tests;
+
+// variables connected by the constraints they appear in together
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hypergraph {
+    pub num_variables: usize,
+    pub edges: Vec<Vec<usize>>,
+}
+
+impl Hypergraph {
+    pub fn from_cnf(cnf: &Cnf) -> Self {
+        let mut hypergraph = Hypergraph {
+            num_variables: cnf.num_variables,
+            edges: Default::default(),
+        };
+        for clause in cnf.clauses.iter() {
+            hypergraph.add_edge(clause.iter().map(|lit| lit.var()).collect());
+        }
+        hypergraph
+    }
+
+    // one edge per top level conjunct, which are the clauses of a CNF
+    pub fn from_propositional(tree: &PropositionalTree) -> Self {
+        let mut hypergraph = Hypergraph {
+            num_variables: tree.num_named(),
+            edges: Default::default(),
+        };
+        let mut conjuncts: Vec<Addr> = Default::default();
+        let mut stack: Vec<Addr> = Default::default();
+        if tree.output().idx.is_addr() {
+            stack.push(tree.output().idx);
+        }
+        while let Some(idx) = stack.pop() {
+            match tree[idx].value {
+                PLogic::And => stack.extend(tree[idx].node.operands().iter().rev()),
+                _ => conjuncts.push(idx),
+            }
+        }
+
+        for conjunct in conjuncts {
+            let mut variables: Vec<usize> = Default::default();
+            let mut stack = vec![conjunct];
+            while let Some(idx) = stack.pop() {
+                match tree[idx].value {
+                    PLogic::Variable { id } => variables.push(id.addr()),
+                    _ => stack.extend(
+                        tree[idx]
+                            .node
+                            .operands()
+                            .iter()
+                            .filter(|child| child.is_addr()),
+                    ),
+                }
+            }
+            hypergraph.add_edge(variables);
+        }
+        hypergraph
+    }
+
+    fn add_edge(&mut self, mut edge: Vec<usize>) {
+        edge.sort();
+        edge.dedup();
+        if !edge.is_empty() {
+            self.edges.push(edge);
+        }
+    }
+
+    // two variables are neighbours when they share an edge
+    pub fn primal(&self) -> Vec<BTreeSet<usize>> {
+        let mut neighbours: Vec<BTreeSet<usize>> = vec![Default::default(); self.num_variables];
+        for edge in self.edges.iter() {
+            for &a in edge.iter() {
+                neighbours[a].extend(edge.iter().filter(|&&b| b != a));
+            }
+        }
+        neighbours
+    }
+
+    // edges of every variable
+    fn incidence(&self) -> Vec<Vec<usize>> {
+        let mut incidence: Vec<Vec<usize>> = vec![Default::default(); self.num_variables];
+        for (idx, edge) in self.edges.iter().enumerate() {
+            for &var in edge.iter() {
+                incidence[var].push(idx);
+            }
+        }
+        incidence
+    }
+}
+
+// greedy elimination of the variable with the lowest cost, ties go to the lowest id
+fn elimination_order<K: Ord, F: Fn(&[BTreeSet<usize>], usize) -> K>(
+    hypergraph: &Hypergraph,
+    cost: F,
+) -> Vec<Addr> {
+    let mut graph = hypergraph.primal();
+    let mut eliminated = vec![false; hypergraph.num_variables];
+    let mut order: Vec<Addr> = Default::default();
+    for _ in 0..hypergraph.num_variables {
+        let var = (0..hypergraph.num_variables)
+            .filter(|&var| !eliminated[var])
+            .min_by_key(|&var| cost(&graph, var))
+            .unwrap();
+
+        let neighbours: Vec<usize> = graph[var].iter().copied().collect();
+        for &a in neighbours.iter() {
+            graph[a].remove(&var);
+            graph[a].extend(neighbours.iter().filter(|&&b| b != a));
+        }
+        graph[var].clear();
+        eliminated[var] = true;
+        order.push(Addr::new(var));
+    }
+    order
+}
+
+pub fn min_degree_order(hypergraph: &Hypergraph) -> Vec<Addr> {
+    elimination_order(hypergraph, |graph, var| graph[var].len())
+}
+
+// number of edges the elimination adds between the neighbours, then degree
+pub fn min_fill_order(hypergraph: &Hypergraph) -> Vec<Addr> {
+    elimination_order(hypergraph, |graph, var| {
+        let neighbours: Vec<usize> = graph[var].iter().copied().collect();
+        let fill: usize = neighbours
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                neighbours[i + 1..]
+                    .iter()
+                    .filter(|b| !graph[*a].contains(b))
+                    .count()
+            })
+            .sum();
+        (fill, neighbours.len())
+    })
+}
+
+// balanced split of the variables cutting few edges, grown by a breadth first
+// search then refined by moving single variables
+fn bisect(hypergraph: &Hypergraph, incidence: &[Vec<usize>], variables: &[usize]) -> Vec<bool> {
+    let mut positions: Vec<Option<usize>> = vec![None; hypergraph.num_variables];
+    for (pos, &var) in variables.iter().enumerate() {
+        positions[var] = Some(pos);
+    }
+    let position = |var: usize| positions[var];
+    let neighbours = |var: usize| {
+        incidence[var]
+            .iter()
+            .flat_map(|&edge| hypergraph.edges[edge].iter().copied())
+            .filter_map(position)
+            .collect::<BTreeSet<usize>>()
+    };
+
+    let half = variables.len() / 2;
+    let mut side = vec![false; variables.len()];
+    let mut visited = vec![false; variables.len()];
+    let mut num_left = 0;
+    for start in 0..variables.len() {
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            if visited[current] || num_left == half {
+                continue;
+            }
+            visited[current] = true;
+            side[current] = true;
+            num_left += 1;
+            queue.extend(neighbours(variables[current]));
+        }
+    }
+
+    // an edge is cut when its variables in the set are on both sides
+    let cut = |side: &[bool], edge: usize| {
+        let mut sides = hypergraph.edges[edge]
+            .iter()
+            .filter_map(|&var| position(var))
+            .map(|pos| side[pos]);
+        match sides.next() {
+            Some(first) => sides.any(|other| other != first),
+            None => false,
+        }
+    };
+    let slack = (variables.len() / 10).max(1);
+    let mut moved = vec![false; variables.len()];
+    loop {
+        let mut best: Option<(usize, isize)> = None;
+        for (pos, &var) in variables.iter().enumerate() {
+            let num_left_after = if side[pos] {
+                num_left - 1
+            } else {
+                num_left + 1
+            };
+            let balanced = num_left_after + slack >= half && num_left_after <= half + slack;
+            if moved[pos] || !balanced || num_left_after == 0 || num_left_after == variables.len() {
+                continue;
+            }
+            let before = incidence[var]
+                .iter()
+                .filter(|&&edge| cut(&side, edge))
+                .count();
+            side[pos] = !side[pos];
+            let after = incidence[var]
+                .iter()
+                .filter(|&&edge| cut(&side, edge))
+                .count();
+            side[pos] = !side[pos];
+            let gain = before as isize - after as isize;
+            if gain > 0 && best.is_none_or(|(_, best_gain)| gain > best_gain) {
+                best = Some((pos, gain));
+            }
+        }
+        match best {
+            Some((pos, _)) => {
+                num_left = if side[pos] {
+                    num_left - 1
+                } else {
+                    num_left + 1
+                };
+                side[pos] = !side[pos];
+                moved[pos] = true;
+            }
+            None => return side,
+        }
+    }
+}
+
+fn bisection_recursive(
+    hypergraph: &Hypergraph,
+    incidence: &[Vec<usize>],
+    variables: &[usize],
+    vtree: &mut Vtree,
+) -> usize {
+    if variables.len() == 1 {
+        return vtree.leaf(Addr::new(variables[0]));
+    }
+    let side = bisect(hypergraph, incidence, variables);
+    let split = |left: bool| -> Vec<usize> {
+        variables
+            .iter()
+            .zip(side.iter())
+            .filter(|&(_, &side)| side == left)
+            .map(|(&var, _)| var)
+            .collect()
+    };
+    let (left, right) = (split(true), split(false));
+    let left = bisection_recursive(hypergraph, incidence, &left, vtree);
+    let right = bisection_recursive(hypergraph, incidence, &right, vtree);
+    vtree.join(left, right)
+}
+
+// variables sharing many edges end up in the same subtree
+pub fn bisection_vtree(hypergraph: &Hypergraph) -> Vtree {
+    let mut vtree: Vtree = Default::default();
+    if hypergraph.num_variables > 0 {
+        let variables: Vec<usize> = (0..hypergraph.num_variables).collect();
+        bisection_recursive(hypergraph, &hypergraph.incidence(), &variables, &mut vtree);
+    }
+    vtree
+}
+
+// atoms grouped by predicate, or by the value of one of their arguments and
+// then by predicate, the other variables of the mapping come last
+pub fn grounded_order<T: Mapping>(
+    tree: &T,
+    grounded: &[Grounded],
+    argument: Option<usize>,
+) -> Vec<Addr> {
+    let mut predicates: Vec<&Grounded> = grounded.iter().collect();
+    predicates.sort_by_key(|g| g.id.addr());
+
+    let mut atoms: Vec<(Option<usize>, usize, usize, Addr)> = Default::default();
+    for (rank, g) in predicates.iter().enumerate() {
+        for (idx, &id) in g.grounded.iter().enumerate() {
+            let key = argument.and_then(|arg| g.arguments(idx).get(arg).copied());
+            atoms.push((key, rank, idx, id));
+        }
+    }
+    atoms.sort_by_key(|&(key, rank, idx, _)| (key, rank, idx));
+
+    let mut placed = vec![false; tree.num_named()];
+    let mut order: Vec<Addr> = Default::default();
+    for (_, _, _, id) in atoms {
+        if !placed[id.addr()] {
+            placed[id.addr()] = true;
+            order.push(id);
+        }
+    }
+    order.extend(
+        (0..tree.num_named())
+            .filter(|&var| !placed[var])
+            .map(Addr::new),
+    );
+    order
+}
@@ -0,0 +1,118 @@
+use crate::{
+    logic::{
+        bdd::{propositional_to_bdd, Bdd},
+        first_order::{FOMut, FirstOrderTree},
+        propositional::Literal,
+        sdd::{propositional_to_sdd, Sdd},
+    },
+    solver::domain::Integer,
+};
+
+use super::*;
+
+// (x0∨x4)∧(x1∨x5)∧(x2∨x6)∧(x3∨x7), large with the natural order
+fn pairs() -> Cnf {
+    let mut cnf = Cnf::new(8);
+    for i in 0..4 {
+        cnf.add_clause(&[
+            Literal::new(Addr::new(i), false),
+            Literal::new(Addr::new(i + 4), false),
+        ]);
+    }
+    cnf
+}
+
+#[test]
+fn elimination() {
+    let mut chain = Cnf::new(5);
+    for i in 0..4 {
+        chain.add_clause(&[
+            Literal::new(Addr::new(i), true),
+            Literal::new(Addr::new(i + 1), false),
+        ]);
+    }
+    let natural: Vec<Addr> = (0..5).map(Addr::new).collect();
+    assert_eq!(min_degree_order(&Hypergraph::from_cnf(&chain)), natural);
+    assert_eq!(min_fill_order(&Hypergraph::from_cnf(&chain)), natural);
+    assert_eq!(
+        Hypergraph::from_propositional(&chain.to_tree()),
+        Hypergraph::from_cnf(&chain)
+    );
+
+    let cnf = pairs();
+    let tree = cnf.to_tree();
+    let order = min_fill_order(&Hypergraph::from_propositional(&tree));
+    assert_eq!(order, min_degree_order(&Hypergraph::from_cnf(&cnf)));
+
+    let mut good = Bdd::with_order(&order).unwrap();
+    let mut bad = Bdd::new(8);
+    let f_good = propositional_to_bdd(&mut good, &tree);
+    let f_bad = propositional_to_bdd(&mut bad, &tree);
+    assert_eq!(good.count(f_good), bad.count(f_bad));
+    assert!(good.size(f_good) < bad.size(f_bad));
+}
+
+#[test]
+fn bisection() {
+    let cnf = pairs();
+    let tree = cnf.to_tree();
+    let vtree = bisection_vtree(&Hypergraph::from_cnf(&cnf));
+    assert_eq!(vtree.size(vtree.root()), 8);
+    for i in 0..4 {
+        let leaf = vtree.leaf_of(Addr::new(i)).unwrap();
+        let other = vtree.leaf_of(Addr::new(i + 4)).unwrap();
+        assert_eq!(vtree.parent(leaf), vtree.parent(other));
+    }
+
+    let natural: Vec<Addr> = (0..8).map(Addr::new).collect();
+    let mut good = Sdd::new(vtree);
+    let mut bad = Sdd::new(Vtree::balanced(&natural));
+    let f_good = propositional_to_sdd(&mut good, &tree);
+    let f_bad = propositional_to_sdd(&mut bad, &tree);
+    assert_eq!(good.count(f_good), bad.count(f_bad));
+    assert!(good.size(f_good) < bad.size(f_bad));
+}
+
+#[test]
+fn grounded() {
+    let input = FirstOrderTree::build(|builder| {
+        builder.every("x", |inner| {
+            inner.every("y", |inner| {
+                inner.or(
+                    |left| left.pred("P", &["x"]),
+                    |right| right.pred("Edge", &["x", "y"]),
+                )
+            })
+        })
+    });
+    let domains = [Integer {
+        vars: vec![
+            input.get_id(&"x".to_string()),
+            input.get_id(&"y".to_string()),
+        ],
+        card: 3,
+    }];
+    let mut tree: PropositionalTree = Default::default();
+    let grounded = Grounded::ground(&input, &mut tree, &domains).unwrap();
+    let names =
+        |order: &[Addr]| -> Vec<String> { order.iter().map(|&id| tree.fmt_named(id)).collect() };
+
+    let by_predicate = names(&grounded_order(&tree, &grounded, None));
+    assert_eq!(by_predicate.len(), 12);
+    let changes = by_predicate
+        .windows(2)
+        .filter(|pair| pair[0][..2] != pair[1][..2])
+        .count();
+    assert_eq!(changes, 1);
+
+    let by_argument = names(&grounded_order(&tree, &grounded, Some(0)));
+    let first: Vec<char> = by_argument
+        .iter()
+        .map(|name| name.chars().nth(name.find('(').unwrap() + 1).unwrap())
+        .collect();
+    assert!(first.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(
+        &by_argument[..4],
+        &["P(0)", "Edge(0, 0)", "Edge(0, 1)", "Edge(0, 2)"]
+    );
+}
@@ -0,0 +1,288 @@
+use crate::tree::Mapping;
+
+use super::{Cnf, Literal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CardinalityEncoding {
+    #[default]
+    SequentialCounter,
+    Totalizer,
+    SortingNetwork,
+}
+
+// encodes cardinality and pseudo-Boolean constraints as clauses, auxiliary
+// variables are allocated as anonymous ones of the mapping and added to the
+// CNF; when count preserving, they are defined
+// by equivalences so that every model of the constraint extends to exactly
+// one model of the clauses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Encoder {
+    pub encoding: CardinalityEncoding,
+    pub count_preserving: bool,
+}
+
+// the mapping first catches up with the variables of the CNF, like conjoin
+fn fresh(cnf: &mut Cnf, vars: &mut impl Mapping) -> Literal {
+    while vars.num_named() < cnf.num_variables {
+        vars.add_anon();
+    }
+    let id = vars.add_anon();
+    cnf.num_variables = cnf.num_variables.max(id.addr() + 1);
+    Literal::new(id, false)
+}
+
+// up adds count >= j -> u[j - 1], down adds u[j - 1] -> count >= j
+#[derive(Debug, Clone, Copy)]
+struct Directions {
+    up: bool,
+    down: bool,
+}
+
+// s[i][j] holds when at least j + 1 of the first i + 1 literals are true,
+// counts above m are saturated
+fn sequential_counter(
+    cnf: &mut Cnf,
+    vars: &mut impl Mapping,
+    lits: &[Literal],
+    m: usize,
+    directions: Directions,
+) -> Vec<Literal> {
+    let mut counts: Vec<Literal> = Default::default();
+    for (i, &x) in lits.iter().enumerate() {
+        let next: Vec<Literal> = (0..(i + 1).min(m)).map(|_| fresh(cnf, vars)).collect();
+        for (j, &count) in next.iter().enumerate() {
+            // count <-> previous or (x and below), below being true for j = 0
+            let previous = counts.get(j).copied();
+            let below = j.checked_sub(1).map(|j| counts.get(j).copied());
+            if directions.up {
+                if let Some(previous) = previous {
+                    cnf.add_clause(&[!previous, count]);
+                }
+                match below {
+                    None => cnf.add_clause(&[!x, count]),
+                    Some(Some(below)) => cnf.add_clause(&[!x, !below, count]),
+                    Some(None) => {}
+                }
+            }
+            if directions.down {
+                let mut clause: Vec<Literal> = vec![!count, x];
+                clause.extend(previous);
+                cnf.add_clause(&clause);
+                if let Some(below) = below {
+                    let mut clause: Vec<Literal> = vec![!count];
+                    clause.extend(previous);
+                    clause.extend(below);
+                    cnf.add_clause(&clause);
+                }
+            }
+        }
+        counts = next;
+    }
+    counts
+}
+
+// generalized totalizer, every node lists the sums it can reach in
+// increasing order with a literal meaning sum >= value, sums above the cap
+// are saturated
+fn totalizer<M: Mapping>(
+    cnf: &mut Cnf,
+    vars: &mut M,
+    terms: &[(Literal, u64)],
+    cap: u64,
+    directions: Directions,
+) -> Vec<(u64, Literal)> {
+    if terms.len() == 1 {
+        let (lit, weight) = terms[0];
+        return vec![(weight.min(cap), lit)];
+    }
+
+    let (left, right) = terms.split_at(terms.len() / 2);
+    let left = totalizer(cnf, vars, left, cap, directions);
+    let right = totalizer(cnf, vars, right, cap, directions);
+
+    let mut values: Vec<u64> = left.iter().chain(right.iter()).map(|x| x.0).collect();
+    for &(a, _) in left.iter() {
+        for &(b, _) in right.iter() {
+            values.push((a + b).min(cap));
+        }
+    }
+    values.sort();
+    values.dedup();
+    let sums: Vec<(u64, Literal)> = values.into_iter().map(|v| (v, fresh(cnf, vars))).collect();
+    let sum = |value: u64| sums[sums.binary_search_by_key(&value, |x| x.0).unwrap()].1;
+
+    if directions.up {
+        for &(a, x) in left.iter().chain(right.iter()) {
+            cnf.add_clause(&[!x, sum(a)]);
+        }
+        for &(a, x) in left.iter() {
+            for &(b, y) in right.iter() {
+                cnf.add_clause(&[!x, !y, sum((a + b).min(cap))]);
+            }
+        }
+    }
+
+    if directions.down {
+        for pair in sums.windows(2) {
+            cnf.add_clause(&[!pair[1].1, pair[0].1]);
+        }
+        // if the children are below their next values, so is the parent
+        let above =
+            |node: &[(u64, Literal)], value: u64| node.iter().find(|x| x.0 > value).map(|x| x.1);
+        let reached = |node: &[(u64, Literal)]| {
+            std::iter::once(0)
+                .chain(node.iter().map(|x| x.0))
+                .collect::<Vec<u64>>()
+        };
+        for &a in reached(&left).iter() {
+            for &b in reached(&right).iter() {
+                if let Some(parent) = above(&sums, a + b) {
+                    let mut clause: Vec<Literal> = vec![!parent];
+                    clause.extend(above(&left, a));
+                    clause.extend(above(&right, b));
+                    cnf.add_clause(&clause);
+                }
+            }
+        }
+    }
+    sums
+}
+
+// Batcher's odd-even merge sort with the true values moved first,
+// comparators touching padding wires past the inputs are left out
+fn sorting_network(
+    cnf: &mut Cnf,
+    vars: &mut impl Mapping,
+    lits: &[Literal],
+    directions: Directions,
+) -> Vec<Literal> {
+    let mut wires = lits.to_vec();
+    let size = lits.len().next_power_of_two();
+
+    let mut p = 1;
+    while p < size {
+        let mut k = p;
+        while k >= 1 {
+            for j in (k % p..size - k).step_by(2 * k) {
+                for i in 0..k.min(size - j - k) {
+                    let (low, high) = (i + j, i + j + k);
+                    if low / (2 * p) != high / (2 * p) || high >= lits.len() {
+                        continue;
+                    }
+
+                    let (a, b) = (wires[low], wires[high]);
+                    let (max, min) = (fresh(cnf, vars), fresh(cnf, vars));
+                    if directions.up {
+                        cnf.add_clause(&[!a, max]);
+                        cnf.add_clause(&[!b, max]);
+                        cnf.add_clause(&[!a, !b, min]);
+                    }
+                    if directions.down {
+                        cnf.add_clause(&[!max, a, b]);
+                        cnf.add_clause(&[!min, a]);
+                        cnf.add_clause(&[!min, b]);
+                    }
+                    wires[low] = max;
+                    wires[high] = min;
+                }
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+    wires
+}
+
+impl Encoder {
+    // u[j] holds when at least j + 1 literals are true, for j below m
+    fn unary(
+        &self,
+        cnf: &mut Cnf,
+        vars: &mut impl Mapping,
+        lits: &[Literal],
+        m: usize,
+        up: bool,
+        down: bool,
+    ) -> Vec<Literal> {
+        if lits.is_empty() {
+            return Default::default();
+        }
+        let directions = Directions {
+            up: up || self.count_preserving,
+            down: down || self.count_preserving,
+        };
+        match self.encoding {
+            CardinalityEncoding::SequentialCounter => {
+                sequential_counter(cnf, vars, lits, m, directions)
+            }
+            CardinalityEncoding::Totalizer => {
+                let terms: Vec<(Literal, u64)> = lits.iter().map(|&lit| (lit, 1)).collect();
+                totalizer(cnf, vars, &terms, m as u64, directions)
+                    .into_iter()
+                    .map(|x| x.1)
+                    .collect()
+            }
+            CardinalityEncoding::SortingNetwork => {
+                let mut outputs = sorting_network(cnf, vars, lits, directions);
+                outputs.truncate(m);
+                outputs
+            }
+        }
+    }
+
+    pub fn at_most_k(&self, cnf: &mut Cnf, vars: &mut impl Mapping, lits: &[Literal], k: usize) {
+        if k >= lits.len() {
+            return;
+        }
+        let outputs = self.unary(cnf, vars, lits, k + 1, true, false);
+        cnf.add_clause(&[!outputs[k]]);
+    }
+
+    pub fn at_least_k(&self, cnf: &mut Cnf, vars: &mut impl Mapping, lits: &[Literal], k: usize) {
+        if k == 0 {
+            return;
+        }
+        if k > lits.len() {
+            cnf.add_clause(&[]);
+            return;
+        }
+        let outputs = self.unary(cnf, vars, lits, k, false, true);
+        cnf.add_clause(&[outputs[k - 1]]);
+    }
+
+    pub fn exactly_k(&self, cnf: &mut Cnf, vars: &mut impl Mapping, lits: &[Literal], k: usize) {
+        if k > lits.len() {
+            cnf.add_clause(&[]);
+            return;
+        }
+        let outputs = self.unary(cnf, vars, lits, k + 1, true, true);
+        if k > 0 {
+            cnf.add_clause(&[outputs[k - 1]]);
+        }
+        if k < lits.len() {
+            cnf.add_clause(&[!outputs[k]]);
+        }
+    }
+
+    // sum of the weights of the true literals at most k, the encoding only
+    // applies to cardinality constraints, weighted ones are always encoded
+    // with a generalized totalizer
+    pub fn pseudo_boolean(
+        &self,
+        cnf: &mut Cnf,
+        vars: &mut impl Mapping,
+        terms: &[(Literal, u64)],
+        k: u64,
+    ) {
+        let terms: Vec<(Literal, u64)> = terms.iter().copied().filter(|x| x.1 > 0).collect();
+        if terms.iter().map(|x| x.1).sum::<u64>() <= k {
+            return;
+        }
+        let directions = Directions {
+            up: true,
+            down: self.count_preserving,
+        };
+        let sums = totalizer(cnf, vars, &terms, k + 1, directions);
+        cnf.add_clause(&[!sums.last().unwrap().1]);
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Not,
+};
+
+use crate::tree::{Addr, IndexedMutRef, IndexedRef, LinkingNode, Mapping};
+
+use super::{PLogic, PMut, PRef, PropositionalTree};
+
+// variable id and polarity packed as 2 * id + neg
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Literal(u32);
+
+impl Literal {
+    #[inline(always)]
+    pub fn new(id: Addr, neg: bool) -> Self {
+        Literal(((id.addr() as u32) << 1) | neg as u32)
+    }
+
+    #[inline(always)]
+    pub fn id(self) -> Addr {
+        Addr::new(self.var())
+    }
+
+    #[inline(always)]
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    #[inline(always)]
+    pub fn is_neg(self) -> bool {
+        self.0 & 1 != 0
+    }
+
+    // index of the literal in arrays of size 2 * num_variables
+    #[inline(always)]
+    pub fn code(self) -> usize {
+        self.0 as usize
+    }
+
+    // value of the literal under an assignment of its variable
+    #[inline(always)]
+    pub fn eval(self, value: bool) -> bool {
+        value != self.is_neg()
+    }
+
+    // DIMACS convention, variable ids start at 1 and negative means negated
+    pub fn from_dimacs(value: i32) -> Self {
+        Literal::new(Addr::new((value.unsigned_abs() - 1) as usize), value < 0)
+    }
+
+    pub fn to_dimacs(self) -> i32 {
+        let id = self.var() as i32 + 1;
+        if self.is_neg() {
+            -id
+        } else {
+            id
+        }
+    }
+}
+
+impl Not for Literal {
+    type Output = Literal;
+
+    #[inline(always)]
+    fn not(self) -> Literal {
+        Literal(self.0 ^ 1)
+    }
+}
+
+pub type Clause = Vec<Literal>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cnf {
+    pub num_variables: usize,
+    pub clauses: Vec<Clause>,
+}
+
+impl Cnf {
+    pub fn new(num_variables: usize) -> Self {
+        Cnf {
+            num_variables,
+            clauses: Default::default(),
+        }
+    }
+
+    // sorted and without duplicated literals, tautologies are dropped
+    pub fn add_clause(&mut self, clause: &[Literal]) {
+        let mut clause = clause.to_vec();
+        clause.sort();
+        clause.dedup();
+        if clause.windows(2).any(|x| x[0] == !x[1]) {
+            return;
+        }
+        if let Some(max) = clause.iter().map(|lit| lit.var()).max() {
+            self.num_variables = self.num_variables.max(max + 1);
+        }
+        self.clauses.push(clause);
+    }
+
+    // the tree must be a conjunction of disjunctions of literals
+    pub fn from_tree(tree: &PropositionalTree) -> Result<Self, &'static str> {
+        let mut cnf = Cnf::new(tree.num_named());
+        if tree.output().idx.is_addr() {
+            collect_conjunction(&tree.output(), &mut cnf)?;
+        }
+        Ok(cnf)
+    }
+
+    pub fn eval(&self, assignment: &[bool]) -> bool {
+        self.clauses
+            .iter()
+            .all(|clause| clause.iter().any(|lit| lit.eval(assignment[lit.var()])))
+    }
+
+    // the empty conjunction is built as x1∨¬x1 and the empty clause as x1∧¬x1,
+    // without variables the tree has no output
+    pub fn to_tree(&self) -> PropositionalTree {
+        let mut tree: PropositionalTree = Default::default();
+        for _ in 0..self.num_variables {
+            tree.add_anon();
+        }
+        if self.num_variables == 0 {
+            return tree;
+        }
+        tree.builder(|builder| {
+            if self.clauses.is_empty() {
+                return builder.or(
+                    |left| left.var(Addr::new(0)),
+                    |right| right.not(|inner| inner.var(Addr::new(0))),
+                );
+            }
+            add_clauses(builder, &self.clauses)
+        });
+        tree
+    }
+
+    // conjunction of the tree with the clauses, variables missing from the
+    // tree are added as anonymous ones
+    pub fn conjoin(&self, tree: &mut PropositionalTree) {
+        while tree.num_named() < self.num_variables.max(1) {
+            tree.add_anon();
+        }
+        if self.clauses.is_empty() {
+            return;
+        }
+
+        let output = tree.output().idx;
+        tree.builder(|builder| {
+            if output.is_none() {
+                return add_clauses(builder, &self.clauses);
+            }
+            builder.and(|_| output, |right| add_clauses(right, &self.clauses))
+        });
+    }
+}
+
+// non empty conjunction of the clauses
+fn add_clauses(builder: &mut IndexedMutRef<PropositionalTree>, clauses: &[Clause]) -> Addr {
+    builder.conjunction(&mut clauses.iter(), |builder, clause| {
+        if clause.is_empty() {
+            builder.and(
+                |left| left.var(Addr::new(0)),
+                |right| right.not(|inner| inner.var(Addr::new(0))),
+            )
+        } else {
+            builder.disjunction(&mut clause.iter(), add_literal)
+        }
+    })
+}
+
+fn add_literal(builder: &mut IndexedMutRef<PropositionalTree>, lit: &Literal) -> Addr {
+    if lit.is_neg() {
+        builder.not(|inner| inner.var(lit.id()))
+    } else {
+        builder.var(lit.id())
+    }
+}
+
+fn collect_conjunction(
+    node: &IndexedRef<PropositionalTree>,
+    cnf: &mut Cnf,
+) -> Result<(), &'static str> {
+    if let PLogic::And = node.as_ref().value {
+        collect_conjunction(&node.left(), cnf)?;
+        collect_conjunction(&node.right(), cnf)
+    } else {
+        let mut clause: Clause = Default::default();
+        collect_clause(node, &mut clause)?;
+        cnf.add_clause(&clause);
+        Ok(())
+    }
+}
+
+fn collect_clause(
+    node: &IndexedRef<PropositionalTree>,
+    clause: &mut Clause,
+) -> Result<(), &'static str> {
+    match node.as_ref().value {
+        PLogic::Variable { id } => clause.push(Literal::new(id, false)),
+        PLogic::Not => match node.inner().as_ref().value {
+            PLogic::Variable { id } => clause.push(Literal::new(id, true)),
+            _ => return Err("Negation of a non-variable in CNF"),
+        },
+        PLogic::Or => {
+            collect_clause(&node.left(), clause)?;
+            collect_clause(&node.right(), clause)?;
+        }
+        PLogic::And => return Err("Conjunction inside a clause"),
+    }
+    Ok(())
+}
+
+// equisatisfiable CNF with one auxiliary variable per binary connective, the
+// first num_named variables are the ones of the tree and every model of the
+// tree extends to exactly one model of the CNF
+pub fn tseitin(tree: &PropositionalTree) -> Cnf {
+    let mut cnf = Cnf::new(tree.num_named());
+    let mut literals: Vec<Option<Literal>> = vec![None; tree.num_nodes()];
+    for idx in tree.topological() {
+        let operands = tree[idx].node.operands();
+        let child = |i: usize| literals[operands[i].addr()].unwrap();
+        literals[idx.addr()] = Some(match tree[idx].value {
+            PLogic::Variable { id } => Literal::new(id, false),
+            PLogic::Not => !child(0),
+            PLogic::And => {
+                let (left, right) = (child(0), child(1));
+                let aux = Literal::new(Addr::new(cnf.num_variables), false);
+                cnf.add_clause(&[!aux, left]);
+                cnf.add_clause(&[!aux, right]);
+                cnf.add_clause(&[aux, !left, !right]);
+                aux
+            }
+            PLogic::Or => {
+                let (left, right) = (child(0), child(1));
+                let aux = Literal::new(Addr::new(cnf.num_variables), false);
+                cnf.add_clause(&[aux, !left]);
+                cnf.add_clause(&[aux, !right]);
+                cnf.add_clause(&[!aux, left, right]);
+                aux
+            }
+        });
+    }
+
+    // an empty tree is false
+    match literals.get(tree.output().idx.addr()).copied().flatten() {
+        Some(output) => cnf.add_clause(&[output]),
+        None => cnf.add_clause(&[]),
+    }
+    cnf
+}
+
+// assigns the literals and propagates unit clauses, None on conflict
+pub fn unit_propagate(
+    clauses: &[Clause],
+    decisions: &[Literal],
+) -> Option<(Vec<Literal>, Vec<Clause>)> {
+    let mut values: HashMap<usize, bool> = Default::default();
+    let mut assigned: Vec<Literal> = Default::default();
+    let mut pending = decisions.to_vec();
+    let mut residual = clauses.to_vec();
+
+    loop {
+        for lit in pending.drain(..) {
+            match values.get(&lit.var()) {
+                Some(&value) if lit.eval(value) => {}
+                Some(_) => return None,
+                None => {
+                    values.insert(lit.var(), !lit.is_neg());
+                    assigned.push(lit);
+                }
+            }
+        }
+
+        let mut simplified: Vec<Clause> = Default::default();
+        for clause in residual {
+            let satisfied = clause
+                .iter()
+                .any(|lit| matches!(values.get(&lit.var()), Some(&value) if lit.eval(value)));
+            if satisfied {
+                continue;
+            }
+            let clause: Clause = clause
+                .into_iter()
+                .filter(|lit| !values.contains_key(&lit.var()))
+                .collect();
+            match clause.len() {
+                0 => return None,
+                1 => pending.push(clause[0]),
+                _ => {}
+            }
+            simplified.push(clause);
+        }
+        residual = simplified;
+
+        if pending.is_empty() {
+            return Some((assigned, residual));
+        }
+    }
+}
+
+fn find(parents: &mut HashMap<usize, usize>, var: usize) -> usize {
+    let parent = *parents.entry(var).or_insert(var);
+    if parent == var {
+        return var;
+    }
+    let root = find(parents, parent);
+    parents.insert(var, root);
+    root
+}
+
+// splits the clauses into sets that do not share any variable, in the order
+// of their root variable so compilations are reproducible
+pub fn split_components(clauses: Vec<Clause>) -> Vec<Vec<Clause>> {
+    let mut parents: HashMap<usize, usize> = Default::default();
+    for clause in clauses.iter() {
+        let first = find(&mut parents, clause[0].var());
+        for lit in clause[1..].iter() {
+            let root = find(&mut parents, lit.var());
+            parents.insert(root, first);
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<Clause>> = Default::default();
+    for clause in clauses {
+        let root = find(&mut parents, clause[0].var());
+        groups.entry(root).or_default().push(clause);
+    }
+    groups.into_values().collect()
+}
@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::tree::Addr;
+
+use super::{split_components, unit_propagate, Clause, Cnf, Literal};
+
+// how a search over the components of a CNF combines its results, the d-DNNF
+// compiler builds circuit nodes and the model counter multiplies counts
+pub trait Decomposition {
+    type Value: Clone;
+
+    fn literal(&mut self, lit: Literal) -> Self::Value;
+    // x ∨ ¬x, for a variable left unconstrained
+    fn free(&mut self, var: usize) -> Self::Value;
+    // conjunction of values over disjoint variables
+    fn product(&mut self, parts: Vec<Self::Value>) -> Self::Value;
+    // disjunction of the two values of a decision variable
+    fn decision(&mut self, positive: Self::Value, negative: Self::Value) -> Self::Value;
+}
+
+// exhaustive DPLL, a formula is the product of its independent components,
+// each one cached on its residual clauses
+pub struct ComponentSearch<D: Decomposition> {
+    pub decomposition: D,
+    // None if the component is unsatisfiable
+    cache: HashMap<Vec<Clause>, Option<D::Value>>,
+}
+
+impl<D: Decomposition> ComponentSearch<D> {
+    pub fn new(decomposition: D) -> Self {
+        ComponentSearch {
+            decomposition,
+            cache: Default::default(),
+        }
+    }
+
+    // the CNF over all its variables, None if unsatisfiable
+    pub fn search(&mut self, cnf: &Cnf) -> Option<D::Value> {
+        if cnf.clauses.iter().any(|clause| clause.is_empty()) {
+            return None;
+        }
+        let scope: Vec<usize> = (0..cnf.num_variables).collect();
+        self.formula(&cnf.clauses, &[], &scope)
+    }
+
+    // conjunction of the decisions and the clauses, over the scope
+    fn formula(
+        &mut self,
+        clauses: &[Clause],
+        decisions: &[Literal],
+        scope: &[usize],
+    ) -> Option<D::Value> {
+        let (assigned, residual) = unit_propagate(clauses, decisions)?;
+
+        let mut covered: HashSet<usize> = assigned.iter().map(|lit| lit.var()).collect();
+        let mut parts: Vec<D::Value> = assigned
+            .iter()
+            .map(|&lit| self.decomposition.literal(lit))
+            .collect();
+        for component in split_components(residual) {
+            covered.extend(component.iter().flatten().map(|lit| lit.var()));
+            parts.push(self.component(component)?);
+        }
+        for &var in scope.iter() {
+            if !covered.contains(&var) {
+                parts.push(self.decomposition.free(var));
+            }
+        }
+        Some(self.decomposition.product(parts))
+    }
+
+    // decision on the most frequent variable of the component
+    fn component(&mut self, mut clauses: Vec<Clause>) -> Option<D::Value> {
+        clauses.sort();
+        if let Some(result) = self.cache.get(&clauses) {
+            return result.clone();
+        }
+
+        let mut occurrences: HashMap<usize, usize> = Default::default();
+        for lit in clauses.iter().flatten() {
+            *occurrences.entry(lit.var()).or_default() += 1;
+        }
+        let mut scope: Vec<usize> = occurrences.keys().copied().collect();
+        scope.sort();
+        let decision = Addr::new(*scope.iter().max_by_key(|var| occurrences[var]).unwrap());
+
+        let positive = self.formula(&clauses, &[Literal::new(decision, false)], &scope);
+        let negative = self.formula(&clauses, &[Literal::new(decision, true)], &scope);
+        let result = match (positive, negative) {
+            (Some(pos), Some(neg)) => Some(self.decomposition.decision(pos, neg)),
+            (pos, neg) => pos.or(neg),
+        };
+
+        self.cache.insert(clauses, result.clone());
+        result
+    }
+}
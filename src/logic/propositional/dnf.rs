@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use crate::{
+    bignum::BigUint,
     logic::propositional::PropositionalTree,
     tree::{Addr, IndexedMutRef, IndexedRef, LinkingNode, Mapping},
 };
@@ -187,36 +188,51 @@ fn print_bool(a: &Vec<bool>) {
     println!("")
 }
 
-pub fn count_propositional(tree: &PropositionalTree) -> usize {
+// parts of the cube that are outside of the other cube, as disjoint cubes
+fn cube_difference(cube: &[Option<bool>], other: &[Option<bool>]) -> Vec<Vec<Option<bool>>> {
+    let disjoint = cube
+        .iter()
+        .zip(other)
+        .any(|(a, b)| matches!((a, b), (Some(a), Some(b)) if a != b));
+    if disjoint {
+        return vec![cube.to_vec()];
+    }
+
+    let mut pieces: Vec<Vec<Option<bool>>> = Default::default();
+    let mut current = cube.to_vec();
+    for (idx, value) in other.iter().enumerate() {
+        if let (None, &Some(value)) = (current[idx], value) {
+            let mut piece = current.clone();
+            piece[idx] = Some(!value);
+            pieces.push(piece);
+            current[idx] = Some(value);
+        }
+    }
+    pieces
+}
+
+pub fn count_propositional(tree: &PropositionalTree) -> BigUint {
     let mut nnf = propositional_to_nnf(tree);
     distribute_nodes(&mut nnf.mut_output());
     let mut clauses: HashSet<Vec<Option<bool>>> = Default::default();
     collect_clauses(&nnf.output(), &mut clauses);
 
-    let mut solutions: HashSet<Vec<bool>> = Default::default();
-
+    // split the terms into pairwise disjoint cubes, each one counts 2^(free variables)
+    let mut disjoint: Vec<Vec<Option<bool>>> = Default::default();
     for clause in clauses {
-        let n = clause.iter().filter(|x| x.is_none()).count();
-
-        for i in 0usize..(1 << n) {
-            let mut fill = i;
-            let current: Vec<bool> = clause
+        let mut pieces = vec![clause];
+        for other in disjoint.iter() {
+            pieces = pieces
                 .iter()
-                .map(|x| match x {
-                    &Some(a) => a,
-                    None => {
-                        let current = fill;
-                        fill >>= 1;
-                        (1 & current) != 0
-                    }
-                })
+                .flat_map(|piece| cube_difference(piece, other))
                 .collect();
-
-            solutions.insert(current);
         }
+        disjoint.extend(pieces);
     }
 
-    solutions.len() as usize
+    disjoint.iter().fold(BigUint::default(), |count, cube| {
+        &count + &BigUint::pow2(cube.iter().filter(|x| x.is_none()).count())
+    })
 }
 
 pub fn nnf_to_dnf(tree: &PropositionalTree) -> PropositionalTree {
@@ -1,6 +1,9 @@
-use crate::{logic::semantic::Eval, tree::IndexedRef};
+use crate::{
+    logic::{semantic::Eval, Semiring, SemiringEval},
+    tree::{Addr, IndexedRef, LinkingNode},
+};
 
-use super::{PLogic, PRef, PropositionalTree};
+use super::{propositional_to_nnf, PLogic, PRef, PropositionalTree};
 
 impl<'a> Eval<bool> for IndexedRef<'a, PropositionalTree> {
     type Output = bool;
@@ -22,3 +25,58 @@ impl Eval<bool> for PropositionalTree {
         self.output().eval(assignment)
     }
 }
+
+// Kleene logic, None when the partial assignment does not decide the formula
+impl<'a> Eval<Option<bool>> for IndexedRef<'a, PropositionalTree> {
+    type Output = Option<bool>;
+
+    fn eval(&self, assignment: &Vec<Option<bool>>) -> Self::Output {
+        match self.as_ref().value {
+            PLogic::Variable { id } => assignment[id.addr()],
+            PLogic::Not => self.inner().eval(assignment).map(|value| !value),
+            PLogic::And => match (self.left().eval(assignment), self.right().eval(assignment)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            PLogic::Or => match (self.left().eval(assignment), self.right().eval(assignment)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Eval<Option<bool>> for PropositionalTree {
+    type Output = Option<bool>;
+
+    fn eval(&self, assignment: &Vec<Option<bool>>) -> Self::Output {
+        self.output().eval(assignment)
+    }
+}
+
+// conjunctions are products and disjunctions sums, evaluated on the negation normal form
+impl SemiringEval for PropositionalTree {
+    fn eval_semiring<S: Semiring, F: Fn(Addr, bool) -> S>(&self, leaf: F) -> S {
+        if self.output().idx.is_none() {
+            return S::zero();
+        }
+        let nnf = propositional_to_nnf(self);
+        let mut values = vec![S::zero(); nnf.num_nodes()];
+        for idx in nnf.topological() {
+            let node = &nnf[idx];
+            let operands = node.node.operands();
+            values[idx.addr()] = match node.value {
+                PLogic::Variable { id } => leaf(id, false),
+                PLogic::Not => match nnf[operands[0]].value {
+                    PLogic::Variable { id } => leaf(id, true),
+                    _ => unreachable!(),
+                },
+                PLogic::And => values[operands[0].addr()].times(&values[operands[1].addr()]),
+                PLogic::Or => values[operands[0].addr()].plus(&values[operands[1].addr()]),
+            };
+        }
+        values.swap_remove(nnf.output().idx.addr())
+    }
+}
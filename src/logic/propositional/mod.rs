@@ -1,4 +1,7 @@
 pub mod builder;
+pub mod cardinality;
+pub mod cnf;
+pub mod components;
 pub mod dnf;
 pub mod eval;
 pub mod nnf;
@@ -8,6 +11,9 @@ pub mod node;
 mod tests;
 
 pub use builder::*;
+pub use cardinality::*;
+pub use cnf::*;
+pub use components::*;
 pub use dnf::*;
 pub use nnf::*;
 pub use node::*;
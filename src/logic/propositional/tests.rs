@@ -1,6 +1,14 @@
 use nnf::propositional_to_nnf;
 
-use crate::{logic::semantic::Eval, tree::IntoAddr};
+use crate::{
+    bignum::BigUint,
+    logic::{
+        semantic::Eval,
+        semiring::{eval_semiring, LogProbability, MaxProduct, Tropical},
+    },
+    solver::{cdcl::Solver, naive, sharpsat},
+    tree::{IntoAddr, Mapping},
+};
 
 use super::*;
 
@@ -128,3 +136,208 @@ fn nnf() {
     let nnf=propositional_to_nnf(&input);
     assert_eq!(format!("{nnf}"),"((A∧(A∨((B∨¬C)∨(A∧C))))∧(D∧¬B))");
 }
+
+#[test]
+fn semiring() {
+    // deterministic and smooth (A∧¬B)∨(¬A∧B) under a double negation
+    let tree = PropositionalTree::build(|builder| {
+        builder.not(|inner| {
+            inner.not(|inner| {
+                inner.or(
+                    |left| {
+                        left.and(
+                            |left| left.var("A"),
+                            |right| right.not(|inner| inner.var("B")),
+                        )
+                    },
+                    |right| {
+                        right.and(
+                            |left| left.not(|inner| inner.var("A")),
+                            |right| right.var("B"),
+                        )
+                    },
+                )
+            })
+        })
+    });
+    let prob = |id: Addr, neg: bool| {
+        let p = [0.3, 0.6][id.addr()];
+        if neg {
+            1.0 - p
+        } else {
+            p
+        }
+    };
+
+    assert!(eval_semiring::<bool, _, _>(&tree, |_, _| true));
+    assert_eq!(eval_semiring::<u128, _, _>(&tree, |_, _| 1), 2);
+
+    let wmc = eval_semiring(&tree, prob);
+    assert!((wmc - (0.3 * 0.4 + 0.7 * 0.6)).abs() < 1e-12);
+    let log_wmc = eval_semiring(&tree, |id, neg| LogProbability(prob(id, neg).ln()));
+    assert!((log_wmc.0.exp() - wmc).abs() < 1e-12);
+
+    let mpe = eval_semiring(&tree, |id, neg| MaxProduct(prob(id, neg)));
+    assert!((mpe.0 - 0.7 * 0.6).abs() < 1e-12);
+    let cost = eval_semiring(&tree, |id, neg| Tropical(-prob(id, neg).ln()));
+    assert!((cost.0 + (0.7f64 * 0.6).ln()).abs() < 1e-12);
+}
+
+#[test]
+fn count() {
+    let tree = PropositionalTree::build(|builder| {
+        builder.or(
+            |left| left.var("A"),
+            |right| {
+                right.and(
+                    |left| left.var("B"),
+                    |right| right.not(|inner| inner.var("C")),
+                )
+            },
+        )
+    });
+    assert_eq!(count_propositional(&tree), BigUint::from(5u64));
+    assert_eq!(naive::count(&tree), BigUint::from(5u64));
+
+    // 128 unconstrained variables on top of A∨B
+    let mut tree = PropositionalTree::build(|builder| {
+        builder.or(|left| left.var("A"), |right| right.var("B"))
+    });
+    for _ in 0..128 {
+        tree.add_anon();
+    }
+    assert_eq!(
+        format!("{}", count_propositional(&tree)),
+        "1020847100762815390390123822295304634368"
+    );
+}
+
+#[test]
+fn cnf() {
+    let tree = PropositionalTree::build(|builder| {
+        builder.and(
+            |left| {
+                left.and(
+                    |left| left.or(|left| left.var("A"), |right| right.not(|inner| inner.var("B"))),
+                    |right| right.or(|left| left.var("B"), |right| right.var("C")),
+                )
+            },
+            |right| right.not(|inner| inner.var("A")),
+        )
+    });
+
+    let cnf = Cnf::from_tree(&tree).unwrap();
+    assert_eq!(cnf.num_variables, 3);
+    assert_eq!(cnf.clauses.len(), 3);
+
+    let back = cnf.to_tree();
+    for x in 0..8 {
+        let assignment = vec![x & 1 != 0, x & 2 != 0, x & 4 != 0];
+        assert_eq!(cnf.eval(&assignment), tree.eval(&assignment));
+        assert_eq!(back.eval(&assignment), tree.eval(&assignment));
+    }
+
+    let lit = Literal::from_dimacs(-3);
+    assert_eq!((lit.var(), lit.is_neg(), lit.to_dimacs()), (2, true, -3));
+    assert_eq!(!lit, Literal::from_dimacs(3));
+
+    let not_cnf = PropositionalTree::build(|builder| {
+        builder.or(
+            |left| left.var("A"),
+            |right| right.and(|left| left.var("B"), |right| right.var("C")),
+        )
+    });
+    assert!(Cnf::from_tree(&not_cnf).is_err());
+}
+
+#[test]
+fn cardinality() {
+    let encodings = [
+        CardinalityEncoding::SequentialCounter,
+        CardinalityEncoding::Totalizer,
+        CardinalityEncoding::SortingNetwork,
+    ];
+    for encoding in encodings {
+        for count_preserving in [false, true] {
+            let encoder = Encoder {
+                encoding,
+                count_preserving,
+            };
+            for n in 0..6 {
+                let lits: Vec<Literal> = (0..n)
+                    .map(|i| Literal::new(Addr::new(i), i % 3 == 2))
+                    .collect();
+                let weights: Vec<u64> = (0..n as u64).map(|i| (3 * i + 1) % 5).collect();
+                for k in 0..n + 2 {
+                    for kind in 0..4 {
+                        let mut cnf = Cnf::new(n);
+                        let mut vars = PropositionalTree::default();
+                        match kind {
+                            0 => encoder.at_most_k(&mut cnf, &mut vars, &lits, k),
+                            1 => encoder.at_least_k(&mut cnf, &mut vars, &lits, k),
+                            2 => encoder.exactly_k(&mut cnf, &mut vars, &lits, k),
+                            _ => {
+                                let terms: Vec<(Literal, u64)> =
+                                    lits.iter().copied().zip(weights.iter().copied()).collect();
+                                encoder.pseudo_boolean(&mut cnf, &mut vars, &terms, k as u64)
+                            }
+                        }
+
+                        let mut solver = Solver::from_cnf(&cnf);
+                        let mut expected = 0u64;
+                        for x in 0..1usize << n {
+                            let assignment: Vec<bool> = (0..n).map(|i| x & (1 << i) != 0).collect();
+                            let count = lits
+                                .iter()
+                                .filter(|lit| lit.eval(assignment[lit.var()]))
+                                .count();
+                            let weight: u64 = lits
+                                .iter()
+                                .zip(weights.iter())
+                                .filter(|(lit, _)| lit.eval(assignment[lit.var()]))
+                                .map(|(_, weight)| weight)
+                                .sum();
+                            let holds = match kind {
+                                0 => count <= k,
+                                1 => count >= k,
+                                2 => count == k,
+                                _ => weight <= k as u64,
+                            };
+                            expected += holds as u64;
+
+                            let assumptions: Vec<Literal> = (0..n)
+                                .map(|i| Literal::new(Addr::new(i), !assignment[i]))
+                                .collect();
+                            assert_eq!(
+                                solver.solve_with(&assumptions).is_some(),
+                                holds,
+                                "{encoding:?} {kind} n={n} k={k} x={x:b}"
+                            );
+                        }
+                        if count_preserving {
+                            assert_eq!(sharpsat::count(&cnf), BigUint::from(expected));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // auxiliary variables of the constraint are added to the tree
+    let mut tree = PropositionalTree::build(|builder| {
+        builder.or(|left| left.var("A"), |right| right.var("B"))
+    });
+    tree.add_named(&"C".to_string());
+    let lits: Vec<Literal> = (0..3).map(|i| Literal::new(Addr::new(i), false)).collect();
+    let mut cnf = Cnf::new(tree.num_named());
+    let encoder = Encoder {
+        count_preserving: true,
+        ..Default::default()
+    };
+    encoder.exactly_k(&mut cnf, &mut tree, &lits, 1);
+    assert!(tree.num_named() > 3);
+    assert_eq!(tree.num_named(), cnf.num_variables);
+    assert!(tree.get_named(Addr::new(3)).is_none());
+    cnf.conjoin(&mut tree);
+    assert_eq!(naive::count(&tree), BigUint::from(2u64));
+}
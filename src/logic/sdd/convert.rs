@@ -0,0 +1,144 @@
+use std::{cell::Cell, collections::HashMap};
+
+use crate::{
+    logic::{
+        circuit::{PCMut, ProbabilisticCircuitTree},
+        propositional::{PLogic, PropositionalTree},
+    },
+    tree::{Addr, IndexedMutRef, LinkingNode, Mapping},
+};
+
+use super::{Sdd, SddNode, SddRef, VtreeNode};
+
+// the vtree must contain the variables of the tree, an empty tree is false
+pub fn propositional_to_sdd(sdd: &mut Sdd, tree: &PropositionalTree) -> SddRef {
+    let mut values = vec![SddRef::FALSE; tree.num_nodes()];
+    for idx in tree.topological() {
+        let operands = tree[idx].node.operands();
+        values[idx.addr()] = match tree[idx].value {
+            PLogic::Variable { id } => sdd.literal(id, false),
+            PLogic::Not => sdd.not(values[operands[0].addr()]),
+            PLogic::And => sdd.and(values[operands[0].addr()], values[operands[1].addr()]),
+            PLogic::Or => sdd.or(values[operands[0].addr()], values[operands[1].addr()]),
+        };
+    }
+    match tree.output().idx.into() {
+        Some(output) => values.swap_remove(output),
+        None => SddRef::FALSE,
+    }
+}
+
+// smooth and structured decomposable, every product splits the variables of
+// a vtree node between its left and right children; the variables are named
+// like the ones of the tree, false has no circuit
+pub fn sdd_to_circuit(
+    sdd: &Sdd,
+    f: SddRef,
+    tree: &PropositionalTree,
+) -> Result<ProbabilisticCircuitTree, &'static str> {
+    let mut circuit: ProbabilisticCircuitTree = Default::default();
+    circuit.copy_named(tree);
+    let num_variables = sdd
+        .vtree()
+        .variables(sdd.vtree().root())
+        .iter()
+        .map(|id| id.addr() + 1)
+        .max()
+        .unwrap_or(0);
+    for _ in tree.num_named()..num_variables {
+        circuit.add_anon();
+    }
+    let satisfiable = Cell::new(true);
+    circuit.builder(|builder| {
+        let mut exporter = Exporter {
+            builder,
+            sdd,
+            free: Default::default(),
+            nodes: Default::default(),
+        };
+        exporter.over(f, sdd.vtree().root()).unwrap_or_else(|| {
+            satisfiable.set(false);
+            Addr::NONE
+        })
+    });
+    match satisfiable.get() {
+        true => Ok(circuit),
+        false => Err("Formula is unsatisfiable"),
+    }
+}
+
+struct Exporter<'a, 'b> {
+    builder: &'a mut IndexedMutRef<'b, ProbabilisticCircuitTree>,
+    sdd: &'a Sdd,
+    free: HashMap<usize, Addr>,
+    nodes: HashMap<SddRef, Addr>,
+}
+
+impl<'a, 'b> Exporter<'a, 'b> {
+    // tautology over the variables of a vtree node
+    fn free(&mut self, vtree: usize) -> Addr {
+        if let Some(&addr) = self.free.get(&vtree) {
+            return addr;
+        }
+        let addr = match self.sdd.vtree().node(vtree) {
+            VtreeNode::Leaf { id } => self
+                .builder
+                .sum(|left| left.var(id), |right| right.not_var(id)),
+            VtreeNode::Internal { left, right } => {
+                let left = self.free(left);
+                let right = self.free(right);
+                self.builder.prod(|_| left, |_| right)
+            }
+        };
+        self.free.insert(vtree, addr);
+        addr
+    }
+
+    // f smoothed over the variables of a vtree node above its own
+    fn over(&mut self, f: SddRef, vtree: usize) -> Option<Addr> {
+        match self.sdd.vtree_of(f) {
+            None if f == SddRef::TRUE => Some(self.free(vtree)),
+            None => None,
+            Some(mut current) => {
+                let mut addr = self.node(f);
+                while current != vtree {
+                    let parent = self.sdd.vtree().parent(current).unwrap();
+                    addr = if self.sdd.vtree().left(parent) == current {
+                        let right = self.free(self.sdd.vtree().right(parent));
+                        self.builder.prod(|_| addr, |_| right)
+                    } else {
+                        let left = self.free(self.sdd.vtree().left(parent));
+                        self.builder.prod(|_| left, |_| addr)
+                    };
+                    current = parent;
+                }
+                Some(addr)
+            }
+        }
+    }
+
+    fn node(&mut self, f: SddRef) -> Addr {
+        if let Some(&addr) = self.nodes.get(&f) {
+            return addr;
+        }
+        let addr = match self.sdd.node(f).clone() {
+            SddNode::Literal { id, neg: false } => self.builder.var(id),
+            SddNode::Literal { id, neg: true } => self.builder.not_var(id),
+            SddNode::Decision { vtree, elements } => {
+                let (left, right) = (self.sdd.vtree().left(vtree), self.sdd.vtree().right(vtree));
+                let mut products: Vec<Addr> = Default::default();
+                for (prime, sub) in elements {
+                    if let Some(sub) = self.over(sub, right) {
+                        let prime = self.over(prime, left).unwrap();
+                        products.push(self.builder.prod(|_| prime, |_| sub));
+                    }
+                }
+                self.builder
+                    .sum_n(&mut products.into_iter(), |_, product| (product, 1.0))
+            }
+            SddNode::False | SddNode::True => unreachable!(),
+        };
+        self.nodes.insert(f, addr);
+        addr
+    }
+}
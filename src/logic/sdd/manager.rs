@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use crate::{bignum::BigUint, tree::Addr};
+
+use super::Vtree;
+
+// index of a node in the manager, 0 and 1 are the terminals
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SddRef(u32);
+
+impl SddRef {
+    pub const FALSE: SddRef = SddRef(0);
+    pub const TRUE: SddRef = SddRef(1);
+
+    #[inline(always)]
+    pub fn is_constant(self) -> bool {
+        self.0 < 2
+    }
+
+    #[inline(always)]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// a decision is normalized for an internal vtree node, primes are over its
+// left variables, subs over its right variables, and the primes partition
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SddNode {
+    False,
+    True,
+    Literal {
+        id: Addr,
+        neg: bool,
+    },
+    Decision {
+        vtree: usize,
+        elements: Vec<(SddRef, SddRef)>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Operation {
+    And,
+    Or,
+}
+
+// compressed and trimmed SDDs sharing one unique table, equivalent functions
+// built in the same manager get the same reference
+#[derive(Debug, Clone)]
+pub struct Sdd {
+    vtree: Vtree,
+    nodes: Vec<SddNode>,
+    unique: HashMap<SddNode, SddRef>,
+    computed: HashMap<(Operation, SddRef, SddRef), SddRef>,
+    negations: HashMap<SddRef, SddRef>,
+}
+
+impl Sdd {
+    pub fn new(vtree: Vtree) -> Self {
+        Sdd {
+            vtree,
+            nodes: vec![SddNode::False, SddNode::True],
+            unique: Default::default(),
+            computed: Default::default(),
+            negations: Default::default(),
+        }
+    }
+
+    pub fn vtree(&self) -> &Vtree {
+        &self.vtree
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn node(&self, f: SddRef) -> &SddNode {
+        &self.nodes[f.index()]
+    }
+
+    // vtree node the SDD is normalized for, None for the terminals
+    pub fn vtree_of(&self, f: SddRef) -> Option<usize> {
+        match self.nodes[f.index()] {
+            SddNode::False | SddNode::True => None,
+            SddNode::Literal { id, .. } => self.vtree.leaf_of(id),
+            SddNode::Decision { vtree, .. } => Some(vtree),
+        }
+    }
+
+    fn unique(&mut self, node: SddNode) -> SddRef {
+        if let Some(&f) = self.unique.get(&node) {
+            return f;
+        }
+        let f = SddRef(self.nodes.len() as u32);
+        self.nodes.push(node.clone());
+        self.unique.insert(node, f);
+        f
+    }
+
+    pub fn literal(&mut self, id: Addr, neg: bool) -> SddRef {
+        assert!(
+            self.vtree.leaf_of(id).is_some(),
+            "Variable not in the vtree"
+        );
+        self.unique(SddNode::Literal { id, neg })
+    }
+
+    pub fn not(&mut self, f: SddRef) -> SddRef {
+        if let Some(&result) = self.negations.get(&f) {
+            return result;
+        }
+        let result = match self.nodes[f.index()].clone() {
+            SddNode::False => SddRef::TRUE,
+            SddNode::True => SddRef::FALSE,
+            SddNode::Literal { id, neg } => self.literal(id, !neg),
+            SddNode::Decision { vtree, elements } => {
+                let mut elements: Vec<(SddRef, SddRef)> = elements
+                    .into_iter()
+                    .map(|(prime, sub)| (prime, self.not(sub)))
+                    .collect();
+                elements.sort();
+                self.unique(SddNode::Decision { vtree, elements })
+            }
+        };
+        self.negations.insert(f, result);
+        self.negations.insert(result, f);
+        result
+    }
+
+    pub fn and(&mut self, f: SddRef, g: SddRef) -> SddRef {
+        self.apply(Operation::And, f, g)
+    }
+
+    pub fn or(&mut self, f: SddRef, g: SddRef) -> SddRef {
+        self.apply(Operation::Or, f, g)
+    }
+
+    fn apply(&mut self, operation: Operation, f: SddRef, g: SddRef) -> SddRef {
+        let (absorbing, neutral) = match operation {
+            Operation::And => (SddRef::FALSE, SddRef::TRUE),
+            Operation::Or => (SddRef::TRUE, SddRef::FALSE),
+        };
+        if f == absorbing || g == absorbing {
+            return absorbing;
+        }
+        if f == neutral || f == g {
+            return g;
+        }
+        if g == neutral {
+            return f;
+        }
+        let (f, g) = if f < g { (f, g) } else { (g, f) };
+        if let Some(&result) = self.computed.get(&(operation, f, g)) {
+            return result;
+        }
+
+        let vtree_f = self.vtree_of(f).unwrap();
+        let vtree_g = self.vtree_of(g).unwrap();
+        let result = if vtree_f == vtree_g && self.vtree.is_leaf(vtree_f) {
+            // opposite literals of the same variable
+            absorbing
+        } else {
+            let vtree = self.vtree.lca(vtree_f, vtree_g);
+            let elements_f = self.elements_at(f, vtree);
+            let elements_g = self.elements_at(g, vtree);
+            let mut elements: Vec<(SddRef, SddRef)> = Default::default();
+            for &(prime_f, sub_f) in elements_f.iter() {
+                for &(prime_g, sub_g) in elements_g.iter() {
+                    let prime = self.and(prime_f, prime_g);
+                    if prime != SddRef::FALSE {
+                        let sub = self.apply(operation, sub_f, sub_g);
+                        elements.push((prime, sub));
+                    }
+                }
+            }
+            self.decision(vtree, elements)
+        };
+
+        self.computed.insert((operation, f, g), result);
+        result
+    }
+
+    // f as a partition at an internal vtree node above or equal to its own
+    fn elements_at(&mut self, f: SddRef, vtree: usize) -> Vec<(SddRef, SddRef)> {
+        match &self.nodes[f.index()] {
+            SddNode::Decision {
+                vtree: own,
+                elements,
+            } if *own == vtree => elements.clone(),
+            _ => {
+                if self
+                    .vtree
+                    .is_descendant(self.vtree_of(f).unwrap(), self.vtree.left(vtree))
+                {
+                    let not_f = self.not(f);
+                    vec![(f, SddRef::TRUE), (not_f, SddRef::FALSE)]
+                } else {
+                    vec![(SddRef::TRUE, f)]
+                }
+            }
+        }
+    }
+
+    fn decision(&mut self, vtree: usize, elements: Vec<(SddRef, SddRef)>) -> SddRef {
+        // compression, the primes of equal subs are merged
+        let mut compressed: Vec<(SddRef, SddRef)> = Default::default();
+        for (prime, sub) in elements {
+            match compressed.iter().position(|&(_, other)| other == sub) {
+                Some(idx) => compressed[idx].0 = self.or(compressed[idx].0, prime),
+                None => compressed.push((prime, sub)),
+            }
+        }
+
+        // trimming, {(⊤, s)} is s and {(p, ⊤), (¬p, ⊥)} is p
+        match compressed[..] {
+            [(_, sub)] => return sub,
+            [(prime, SddRef::TRUE), (_, SddRef::FALSE)]
+            | [(_, SddRef::FALSE), (prime, SddRef::TRUE)] => return prime,
+            _ => {}
+        }
+
+        compressed.sort();
+        self.unique(SddNode::Decision {
+            vtree,
+            elements: compressed,
+        })
+    }
+
+    pub fn eval(&self, f: SddRef, assignment: &[bool]) -> bool {
+        match &self.nodes[f.index()] {
+            SddNode::False => false,
+            SddNode::True => true,
+            SddNode::Literal { id, neg } => assignment[id.addr()] != *neg,
+            SddNode::Decision { elements, .. } => elements
+                .iter()
+                .find(|(prime, _)| self.eval(*prime, assignment))
+                .is_some_and(|&(_, sub)| self.eval(sub, assignment)),
+        }
+    }
+
+    // decision nodes reachable from f, every child before its parents
+    pub fn decisions(&self, f: SddRef) -> Vec<SddRef> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![f];
+        let mut decisions: Vec<SddRef> = Default::default();
+        while let Some(current) = stack.pop() {
+            if visited[current.index()] {
+                continue;
+            }
+            visited[current.index()] = true;
+            if let SddNode::Decision { elements, .. } = &self.nodes[current.index()] {
+                decisions.push(current);
+                stack.extend(elements.iter().flat_map(|&(prime, sub)| [prime, sub]));
+            }
+        }
+        // children are always created before their parents
+        decisions.sort();
+        decisions
+    }
+
+    // total number of elements, the usual measure of SDD size
+    pub fn size(&self, f: SddRef) -> usize {
+        self.decisions(f)
+            .iter()
+            .map(|x| match &self.nodes[x.index()] {
+                SddNode::Decision { elements, .. } => elements.len(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    // models of f over the variables below a vtree node containing it
+    fn count_over(&self, f: SddRef, vtree: usize, counts: &HashMap<SddRef, BigUint>) -> BigUint {
+        let size = self.vtree.size(vtree);
+        match self.nodes[f.index()] {
+            SddNode::False => BigUint::default(),
+            SddNode::True => BigUint::pow2(size),
+            SddNode::Literal { .. } => BigUint::pow2(size - 1),
+            SddNode::Decision { vtree: own, .. } => &counts[&f] << (size - self.vtree.size(own)),
+        }
+    }
+
+    // models over all the variables of the vtree
+    pub fn count(&self, f: SddRef) -> BigUint {
+        let mut counts: HashMap<SddRef, BigUint> = Default::default();
+        for current in self.decisions(f) {
+            if let SddNode::Decision { vtree, elements } = &self.nodes[current.index()] {
+                let (left, right) = (self.vtree.left(*vtree), self.vtree.right(*vtree));
+                let count = elements
+                    .iter()
+                    .fold(BigUint::default(), |count, &(prime, sub)| {
+                        let models = &self.count_over(prime, left, &counts)
+                            * &self.count_over(sub, right, &counts);
+                        &count + &models
+                    });
+                counts.insert(current, count);
+            }
+        }
+        self.count_over(f, self.vtree.root(), &counts)
+    }
+}
@@ -0,0 +1,10 @@
+pub mod convert;
+pub mod manager;
+pub mod vtree;
+
+#[cfg(test)]
+mod tests;
+
+pub use convert::*;
+pub use manager::*;
+pub use vtree::*;
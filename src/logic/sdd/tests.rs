@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use crate::{
+    bignum::BigUint,
+    logic::{
+        circuit::PCicruit,
+        eval_semiring,
+        propositional::{Cnf, Literal, PMut, PropositionalTree},
+        Eval,
+    },
+    random::Rng,
+    solver::naive,
+    tree::{Addr, LinkingNode, Mapping},
+};
+
+use super::*;
+
+fn random_cnf(rng: &mut Rng, num_variables: usize, num_clauses: usize) -> Cnf {
+    let mut cnf = Cnf::new(num_variables);
+    for _ in 0..num_clauses {
+        let clause: Vec<Literal> = (0..3)
+            .map(|_| Literal::new(Addr::new(rng.below(num_variables)), rng.next_bool()))
+            .collect();
+        cnf.add_clause(&clause);
+    }
+    cnf
+}
+
+#[test]
+fn vtree() {
+    let order: Vec<Addr> = (0..5).map(Addr::new).collect();
+    let vtree = Vtree::balanced(&order);
+    assert_eq!(vtree.num_nodes(), 9);
+    assert_eq!(vtree.size(vtree.root()), 5);
+    assert_eq!(vtree.variables(vtree.root()), order);
+
+    let leaf = |id| vtree.leaf_of(Addr::new(id)).unwrap();
+    assert_eq!(vtree.lca(leaf(0), leaf(1)), vtree.parent(leaf(0)).unwrap());
+    assert_eq!(vtree.lca(leaf(0), leaf(4)), vtree.root());
+    assert!(vtree.is_descendant(leaf(3), vtree.root()));
+    assert!(!vtree.is_descendant(leaf(3), leaf(2)));
+
+    let linear = Vtree::right_linear(&order);
+    assert!(linear.is_leaf(linear.left(linear.root())));
+    assert_eq!(linear.depth(linear.leaf_of(Addr::new(4)).unwrap()), 4);
+    let linear = Vtree::left_linear(&order);
+    assert!(linear.is_leaf(linear.right(linear.root())));
+}
+
+#[test]
+fn apply() {
+    let order: Vec<Addr> = (0..4).map(Addr::new).collect();
+    let mut sdd = Sdd::new(Vtree::balanced(&order));
+    let a = sdd.literal(Addr::new(0), false);
+    let c = sdd.literal(Addr::new(2), false);
+    let not_a = sdd.not(a);
+    let not_c = sdd.not(c);
+
+    // De Morgan gives the same node
+    let left = sdd.or(a, c);
+    let right = sdd.and(not_a, not_c);
+    let right = sdd.not(right);
+    assert_eq!(left, right);
+    assert_eq!(sdd.count(left), BigUint::from(12u64));
+
+    assert_eq!(sdd.and(left, right), left);
+    assert_eq!(sdd.and(left, not_a), sdd.and(not_a, c));
+    let not_left = sdd.not(left);
+    assert_eq!(sdd.or(left, not_left), SddRef::TRUE);
+    assert_eq!(sdd.and(left, not_left), SddRef::FALSE);
+    assert_eq!(sdd.count(SddRef::TRUE), BigUint::from(16u64));
+}
+
+#[test]
+fn compile() {
+    let mut rng = Rng::new(3);
+    let order: Vec<Addr> = (0..7).map(Addr::new).collect();
+    for num_clauses in [1, 6, 12, 20, 35] {
+        let tree = random_cnf(&mut rng, 7, num_clauses).to_tree();
+        let expected = naive::count(&tree);
+
+        for vtree in [
+            Vtree::balanced(&order),
+            Vtree::right_linear(&order),
+            Vtree::left_linear(&order),
+        ] {
+            let mut sdd = Sdd::new(vtree);
+            let f = propositional_to_sdd(&mut sdd, &tree);
+            let g = propositional_to_sdd(&mut sdd, &tree);
+            assert_eq!(f, g);
+            assert_eq!(sdd.count(f), expected);
+
+            let pc = match sdd_to_circuit(&sdd, f, &tree) {
+                Ok(pc) => pc,
+                Err(e) => {
+                    assert_eq!(expected, BigUint::default());
+                    assert_eq!(e, "Formula is unsatisfiable");
+                    continue;
+                }
+            };
+            assert_eq!(eval_semiring(&pc, |_, _| BigUint::from(1u64)), expected);
+            for x in 0..128 {
+                let assignment: Vec<bool> = (0..7).map(|i| x & (1 << i) != 0).collect();
+                assert_eq!(sdd.eval(f, &assignment), tree.eval(&assignment));
+                assert_eq!(pc.eval(&assignment), tree.eval(&assignment) as u8 as f32);
+            }
+
+            // the children of every product split a vtree node
+            let vtree = sdd.vtree();
+            let mut scopes: Vec<HashSet<usize>> = vec![Default::default(); pc.num_nodes()];
+            for idx in pc.topological() {
+                let operands = pc[idx].node.operands();
+                scopes[idx.addr()] = match pc[idx].value {
+                    PCicruit::Variable { id, .. } => HashSet::from([id.addr()]),
+                    _ => &scopes[operands[0].addr()] | &scopes[operands[1].addr()],
+                };
+                if let PCicruit::Product = pc[idx].value {
+                    let leaves: Vec<usize> = scopes[idx.addr()]
+                        .iter()
+                        .map(|&id| vtree.leaf_of(Addr::new(id)).unwrap())
+                        .collect();
+                    let node = leaves[1..]
+                        .iter()
+                        .fold(leaves[0], |lca, &leaf| vtree.lca(lca, leaf));
+                    let split: HashSet<usize> = vtree
+                        .variables(vtree.left(node))
+                        .iter()
+                        .map(|id| id.addr())
+                        .collect();
+                    assert!(scopes[operands[0].addr()].is_subset(&split));
+                    assert!(scopes[operands[1].addr()].is_disjoint(&split));
+                }
+            }
+        }
+    }
+    // false has no circuit, the variables keep the names of the tree
+    let tree = PropositionalTree::build(|builder| {
+        builder.and(
+            |left| left.var("A"),
+            |right| right.not(|inner| inner.var("B")),
+        )
+    });
+    let mut sdd = Sdd::new(Vtree::balanced(&[Addr::new(0), Addr::new(1)]));
+    let f = propositional_to_sdd(&mut sdd, &tree);
+    let pc = sdd_to_circuit(&sdd, f, &tree).unwrap();
+    assert_eq!(pc.get_id(&"B".to_string()), Addr::new(1));
+    assert_eq!(pc.eval(&vec![true, false]), 1.0);
+    assert_eq!(
+        sdd_to_circuit(&sdd, SddRef::FALSE, &tree),
+        Err("Formula is unsatisfiable")
+    );
+}
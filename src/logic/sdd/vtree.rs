@@ -0,0 +1,188 @@
+use crate::tree::Addr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VtreeNode {
+    Leaf { id: Addr },
+    Internal { left: usize, right: usize },
+}
+
+// full binary tree with one variable per leaf, the last node added is the root
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vtree {
+    nodes: Vec<VtreeNode>,
+    parents: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+    leaves: Vec<Option<usize>>,
+}
+
+impl Vtree {
+    pub fn leaf(&mut self, id: Addr) -> usize {
+        if self.leaves.len() <= id.addr() {
+            self.leaves.resize(id.addr() + 1, None);
+        }
+        assert!(
+            self.leaves[id.addr()].is_none(),
+            "Variable already in the vtree"
+        );
+        let idx = self.push(VtreeNode::Leaf { id }, 1);
+        self.leaves[id.addr()] = Some(idx);
+        idx
+    }
+
+    pub fn join(&mut self, left: usize, right: usize) -> usize {
+        assert!(self.parents[left].is_none() && self.parents[right].is_none() && left != right);
+        let idx = self.push(
+            VtreeNode::Internal { left, right },
+            self.sizes[left] + self.sizes[right],
+        );
+        self.parents[left] = Some(idx);
+        self.parents[right] = Some(idx);
+        idx
+    }
+
+    fn push(&mut self, node: VtreeNode, size: usize) -> usize {
+        self.nodes.push(node);
+        self.parents.push(None);
+        self.sizes.push(size);
+        self.nodes.len() - 1
+    }
+
+    pub fn balanced(order: &[Addr]) -> Self {
+        let mut vtree: Vtree = Default::default();
+        let mut layer: Vec<usize> = order.iter().map(|&id| vtree.leaf(id)).collect();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [left, right] => vtree.join(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+        }
+        vtree
+    }
+
+    // every left child is a leaf, the SDDs are then OBDDs over the order
+    pub fn right_linear(order: &[Addr]) -> Self {
+        let mut vtree: Vtree = Default::default();
+        let mut leaves: Vec<usize> = order.iter().map(|&id| vtree.leaf(id)).collect();
+        if let Some(mut current) = leaves.pop() {
+            while let Some(left) = leaves.pop() {
+                current = vtree.join(left, current);
+            }
+        }
+        vtree
+    }
+
+    pub fn left_linear(order: &[Addr]) -> Self {
+        let mut vtree: Vtree = Default::default();
+        let mut leaves = order.iter().map(|&id| vtree.leaf(id));
+        if let Some(mut current) = leaves.next() {
+            for right in leaves.collect::<Vec<usize>>() {
+                current = vtree.join(current, right);
+            }
+        }
+        vtree
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn node(&self, idx: usize) -> VtreeNode {
+        self.nodes[idx]
+    }
+
+    pub fn parent(&self, idx: usize) -> Option<usize> {
+        self.parents[idx]
+    }
+
+    pub fn is_leaf(&self, idx: usize) -> bool {
+        matches!(self.nodes[idx], VtreeNode::Leaf { .. })
+    }
+
+    pub fn left(&self, idx: usize) -> usize {
+        match self.nodes[idx] {
+            VtreeNode::Internal { left, .. } => left,
+            VtreeNode::Leaf { .. } => panic!("A vtree leaf has no children"),
+        }
+    }
+
+    pub fn right(&self, idx: usize) -> usize {
+        match self.nodes[idx] {
+            VtreeNode::Internal { right, .. } => right,
+            VtreeNode::Leaf { .. } => panic!("A vtree leaf has no children"),
+        }
+    }
+
+    // number of variables below the node
+    pub fn size(&self, idx: usize) -> usize {
+        self.sizes[idx]
+    }
+
+    pub fn leaf_of(&self, id: Addr) -> Option<usize> {
+        self.leaves.get(id.addr()).copied().flatten()
+    }
+
+    // variables below the node, from left to right
+    pub fn variables(&self, idx: usize) -> Vec<Addr> {
+        let mut variables: Vec<Addr> = Default::default();
+        let mut stack = vec![idx];
+        while let Some(current) = stack.pop() {
+            match self.nodes[current] {
+                VtreeNode::Leaf { id } => variables.push(id),
+                VtreeNode::Internal { left, right } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+        variables
+    }
+
+    pub fn depth(&self, idx: usize) -> usize {
+        let mut depth = 0;
+        let mut current = idx;
+        while let Some(parent) = self.parents[current] {
+            depth += 1;
+            current = parent;
+        }
+        depth
+    }
+
+    // whether idx is in the subtree rooted at ancestor, itself included
+    pub fn is_descendant(&self, idx: usize, ancestor: usize) -> bool {
+        let mut current = idx;
+        loop {
+            if current == ancestor {
+                return true;
+            }
+            match self.parents[current] {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn lca(&self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = (a, b);
+        let (mut depth_a, mut depth_b) = (self.depth(a), self.depth(b));
+        while depth_a > depth_b {
+            a = self.parents[a].unwrap();
+            depth_a -= 1;
+        }
+        while depth_b > depth_a {
+            b = self.parents[b].unwrap();
+            depth_b -= 1;
+        }
+        while a != b {
+            a = self.parents[a].unwrap();
+            b = self.parents[b].unwrap();
+        }
+        a
+    }
+}
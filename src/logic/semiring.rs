@@ -0,0 +1,218 @@
+use num_traits::{One, Zero};
+
+use crate::{
+    bignum::{BigRational, BigUint},
+    logic::circuit::{log_sum_exp, Weight},
+    tree::Addr,
+};
+
+pub trait Semiring: Clone {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn plus(&self, other: &Self) -> Self;
+    fn times(&self, other: &Self) -> Self;
+
+    // how the weight of a circuit sum branch is lifted into the semiring
+    fn weight<W: Weight>(weight: W) -> Self;
+}
+
+pub trait SemiringEval {
+    // leaf is called with the variable id and whether the literal is negated
+    fn eval_semiring<S: Semiring, F: Fn(Addr, bool) -> S>(&self, leaf: F) -> S;
+}
+
+pub fn eval_semiring<S: Semiring, T: SemiringEval, F: Fn(Addr, bool) -> S>(tree: &T, leaf: F) -> S {
+    tree.eval_semiring(leaf)
+}
+
+// satisfiability
+impl Semiring for bool {
+    fn zero() -> Self {
+        false
+    }
+
+    fn one() -> Self {
+        true
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        *self || *other
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        *self && *other
+    }
+
+    fn weight<W: Weight>(weight: W) -> Self {
+        weight.prob() != 0.0
+    }
+}
+
+// model counting, weights are ignored
+impl Semiring for u128 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn one() -> Self {
+        1
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        self.checked_add(*other).expect("Model count overflow")
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        self.checked_mul(*other).expect("Model count overflow")
+    }
+
+    fn weight<W: Weight>(_: W) -> Self {
+        1
+    }
+}
+
+// exact model counting
+impl Semiring for BigUint {
+    fn zero() -> Self {
+        Zero::zero()
+    }
+
+    fn one() -> Self {
+        One::one()
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn weight<W: Weight>(_: W) -> Self {
+        One::one()
+    }
+}
+
+// exact weighted model counting
+impl Semiring for BigRational {
+    fn zero() -> Self {
+        Zero::zero()
+    }
+
+    fn one() -> Self {
+        One::one()
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn weight<W: Weight>(weight: W) -> Self {
+        BigRational::from_f64(weight.prob()).expect("Invalid weight")
+    }
+}
+
+// probability and weighted model counting
+impl Semiring for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn weight<W: Weight>(weight: W) -> Self {
+        weight.prob()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LogProbability(pub f64);
+
+impl Semiring for LogProbability {
+    fn zero() -> Self {
+        LogProbability(f64::NEG_INFINITY)
+    }
+
+    fn one() -> Self {
+        LogProbability(0.0)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        LogProbability(log_sum_exp(self.0, other.0))
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        LogProbability(self.0 + other.0)
+    }
+
+    fn weight<W: Weight>(weight: W) -> Self {
+        LogProbability(weight.log_prob())
+    }
+}
+
+// most probable explanation (Viterbi)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaxProduct(pub f64);
+
+impl Semiring for MaxProduct {
+    fn zero() -> Self {
+        MaxProduct(0.0)
+    }
+
+    fn one() -> Self {
+        MaxProduct(1.0)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        MaxProduct(self.0.max(other.0))
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        MaxProduct(self.0 * other.0)
+    }
+
+    fn weight<W: Weight>(weight: W) -> Self {
+        MaxProduct(weight.prob())
+    }
+}
+
+// min-plus over costs, a weight w costs -ln(w)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tropical(pub f64);
+
+impl Semiring for Tropical {
+    fn zero() -> Self {
+        Tropical(f64::INFINITY)
+    }
+
+    fn one() -> Self {
+        Tropical(0.0)
+    }
+
+    fn plus(&self, other: &Self) -> Self {
+        Tropical(self.0.min(other.0))
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        Tropical(self.0 + other.0)
+    }
+
+    fn weight<W: Weight>(weight: W) -> Self {
+        Tropical(-weight.log_prob())
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    bignum::BigUint,
+    logic::propositional::{tseitin, Cnf, Literal, PropositionalTree},
+    random::Rng,
+    tree::{Addr, Mapping},
+};
+
+use super::cdcl::Solver;
+
+// the estimate is within a factor 1 + epsilon of the count with probability
+// at least 1 - delta
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApproxMc {
+    pub epsilon: f64,
+    pub delta: f64,
+    pub seed: u64,
+}
+
+impl Default for ApproxMc {
+    fn default() -> Self {
+        ApproxMc {
+            epsilon: 0.8,
+            delta: 0.2,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApproxCount {
+    pub estimate: BigUint,
+    pub epsilon: f64,
+    pub delta: f64,
+    // the formula had fewer models than the threshold and was counted exactly
+    pub exact: bool,
+    // cell count times number of cells of every successful round
+    pub round_estimates: Vec<BigUint>,
+    pub failed_rounds: usize,
+}
+
+impl ApproxCount {
+    pub fn lower_bound(&self) -> f64 {
+        self.estimate.to_f64() / (1.0 + self.epsilon)
+    }
+
+    pub fn upper_bound(&self) -> f64 {
+        self.estimate.to_f64() * (1.0 + self.epsilon)
+    }
+}
+
+impl fmt::Display for ApproxCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exact {
+            write!(f, "{} (exact)", self.estimate)
+        } else {
+            write!(
+                f,
+                "{} in [{:.3e}, {:.3e}] with probability {:.3} ({} rounds, {} failed)",
+                self.estimate,
+                self.lower_bound(),
+                self.upper_bound(),
+                1.0 - self.delta,
+                self.round_estimates.len() + self.failed_rounds,
+                self.failed_rounds
+            )
+        }
+    }
+}
+
+// distinct models projected on the variables, at most limit of them
+pub(super) fn bounded_models(
+    mut solver: Solver,
+    projection: &[Addr],
+    limit: usize,
+) -> Vec<Vec<bool>> {
+    let mut models: Vec<Vec<bool>> = Default::default();
+    while models.len() < limit {
+        let Some(model) = solver.solve() else {
+            break;
+        };
+        let blocking: Vec<Literal> = projection
+            .iter()
+            .map(|&id| Literal::new(id, model[id.addr()]))
+            .collect();
+        solver.add_clause(&blocking);
+        models.push(projection.iter().map(|id| model[id.addr()]).collect());
+    }
+    models
+}
+
+// m random XORs over the projection, each variable taken with probability 1/2
+pub(super) fn random_xors(rng: &mut Rng, projection: &[Addr], m: usize) -> Vec<(Vec<Addr>, bool)> {
+    (0..m)
+        .map(|_| {
+            let vars = projection
+                .iter()
+                .copied()
+                .filter(|_| rng.next_bool())
+                .collect();
+            (vars, rng.next_bool())
+        })
+        .collect()
+}
+
+impl ApproxMc {
+    fn threshold(&self) -> usize {
+        let epsilon = self.epsilon;
+        (1.0 + 9.84 * (1.0 + epsilon / (1.0 + epsilon)) * (1.0 + 1.0 / epsilon).powi(2)).ceil()
+            as usize
+    }
+
+    fn rounds(&self) -> usize {
+        (17.0 * (3.0 / self.delta).log2()).ceil() as usize
+    }
+
+    // the projection should hold the independent variables, auxiliary
+    // variables determined by them do not change the count
+    // fails when no round found a cell with fewer models than the threshold
+    pub fn count(&self, cnf: &Cnf, projection: &[Addr]) -> Result<ApproxCount, &'static str> {
+        let threshold = self.threshold();
+        let base = Solver::from_cnf(cnf);
+        let mut result = ApproxCount {
+            estimate: BigUint::default(),
+            epsilon: self.epsilon,
+            delta: self.delta,
+            exact: false,
+            round_estimates: Default::default(),
+            failed_rounds: 0,
+        };
+
+        let count = bounded_models(base.clone(), projection, threshold).len();
+        if count < threshold {
+            result.estimate = BigUint::from(count);
+            result.exact = true;
+            return Ok(result);
+        }
+
+        let mut rng = Rng::new(self.seed);
+        for _ in 0..self.rounds() {
+            // nested cells, the first m random XORs of the round define a cell
+            let xors = random_xors(&mut rng, projection, projection.len());
+            let mut cells: HashMap<usize, usize> = Default::default();
+            let mut cell = |m: usize| {
+                *cells.entry(m).or_insert_with(|| {
+                    let mut solver = base.clone();
+                    for (vars, parity) in xors[..m].iter() {
+                        solver.add_xor(vars, *parity);
+                    }
+                    bounded_models(solver, projection, threshold).len()
+                })
+            };
+
+            // smallest number of XORs leaving fewer models than the threshold
+            let (mut low, mut high) = (0, projection.len());
+            if cell(high) >= threshold {
+                result.failed_rounds += 1;
+                continue;
+            }
+            while high - low > 1 {
+                let middle = (low + high) / 2;
+                if cell(middle) >= threshold {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            match cell(high) {
+                0 => result.failed_rounds += 1,
+                count => result.round_estimates.push(&BigUint::from(count) << high),
+            }
+        }
+
+        let mut estimates = result.round_estimates.clone();
+        if estimates.is_empty() {
+            return Err("Every round failed");
+        }
+        estimates.sort();
+        result.estimate = estimates.swap_remove(estimates.len() / 2);
+        Ok(result)
+    }
+
+    pub fn count_propositional(
+        &self,
+        tree: &PropositionalTree,
+    ) -> Result<ApproxCount, &'static str> {
+        let projection: Vec<Addr> = (0..tree.num_named()).map(Addr::new).collect();
+        self.count(&tseitin(tree), &projection)
+    }
+}
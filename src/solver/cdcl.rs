@@ -0,0 +1,468 @@
+use std::{collections::BinaryHeap, mem};
+
+use crate::{
+    logic::propositional::{Clause, Cnf, Literal},
+    tree::Addr,
+};
+
+const ACTIVITY_DECAY: f64 = 0.95;
+const RESTART_UNIT: usize = 100;
+
+// 1, 1, 2, 1, 1, 2, 4, 1, ... restart lengths in units of conflicts
+fn luby(mut idx: usize) -> usize {
+    let mut size = 1;
+    let mut power = 1;
+    while size < idx + 1 {
+        size = 2 * size + 1;
+        power *= 2;
+    }
+    while size - 1 != idx {
+        size = (size - 1) / 2;
+        power /= 2;
+        idx %= size;
+    }
+    power
+}
+
+// conflict driven clause learning with two watched literals, 1-UIP learning,
+// VSIDS decisions, phase saving and Luby restarts
+#[derive(Debug, Clone, Default)]
+pub struct Solver {
+    clauses: Vec<Clause>,
+    // clauses watching each literal, indexed by Literal::code
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Literal>,
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    // lazy max heap on activity, stale entries are skipped when popped
+    heap: BinaryHeap<(u64, usize)>,
+    phases: Vec<bool>,
+    unsatisfiable: bool,
+    // failed assumptions of the last call to solve_with
+    core: Vec<Literal>,
+    pub num_conflicts: usize,
+    pub num_decisions: usize,
+}
+
+impl Solver {
+    pub fn new(num_variables: usize) -> Self {
+        let mut solver = Solver {
+            increment: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..num_variables {
+            solver.add_variable();
+        }
+        solver
+    }
+
+    pub fn from_cnf(cnf: &Cnf) -> Self {
+        let mut solver = Solver::new(cnf.num_variables);
+        for clause in cnf.clauses.iter() {
+            solver.add_clause(clause);
+        }
+        solver
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn add_variable(&mut self) -> Addr {
+        let var = self.values.len();
+        self.watches.extend([Vec::new(), Vec::new()]);
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.heap.push((0f64.to_bits(), var));
+        Addr::new(var)
+    }
+
+    #[inline(always)]
+    fn value(&self, lit: Literal) -> Option<bool> {
+        self.values[lit.var()].map(|value| lit.eval(value))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    // false once the clauses are known to be unsatisfiable
+    pub fn add_clause(&mut self, clause: &[Literal]) -> bool {
+        if self.unsatisfiable {
+            return false;
+        }
+        self.backtrack(0);
+        while clause.iter().any(|lit| lit.var() >= self.num_variables()) {
+            self.add_variable();
+        }
+
+        let mut clause = clause.to_vec();
+        clause.sort();
+        clause.dedup();
+        if clause.windows(2).any(|x| x[0] == !x[1])
+            || clause.iter().any(|&lit| self.value(lit) == Some(true))
+        {
+            return true;
+        }
+        clause.retain(|&lit| self.value(lit).is_none());
+
+        match clause.len() {
+            0 => self.unsatisfiable = true,
+            1 => {
+                self.enqueue(clause[0], None);
+                self.unsatisfiable = self.propagate().is_some();
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+        !self.unsatisfiable
+    }
+
+    // x1⊕...⊕xn = parity, long constraints are chained through auxiliary
+    // variables so that each piece needs at most 8 clauses
+    pub fn add_xor(&mut self, vars: &[Addr], parity: bool) -> bool {
+        // repeated variables cancel out
+        let mut vars: Vec<Addr> = vars.to_vec();
+        vars.sort_by_key(|id| id.addr());
+        let mut reduced: Vec<Addr> = Default::default();
+        for id in vars {
+            if reduced.last() == Some(&id) {
+                reduced.pop();
+            } else {
+                reduced.push(id);
+            }
+        }
+
+        while reduced.len() > 4 {
+            let aux = self.add_variable();
+            let mut piece: Vec<Addr> = reduced.drain(..3).collect();
+            piece.push(aux);
+            if !self.add_xor_clauses(&piece, false) {
+                return false;
+            }
+            reduced.push(aux);
+        }
+        self.add_xor_clauses(&reduced, parity)
+    }
+
+    // one clause excluding each assignment of the wrong parity
+    fn add_xor_clauses(&mut self, vars: &[Addr], parity: bool) -> bool {
+        for x in 0..1usize << vars.len() {
+            if (x.count_ones() % 2 == 1) == parity {
+                continue;
+            }
+            let clause: Vec<Literal> = vars
+                .iter()
+                .enumerate()
+                .map(|(i, &id)| Literal::new(id, x & (1 << i) != 0))
+                .collect();
+            if !self.add_clause(&clause) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn attach(&mut self, clause: Clause) -> usize {
+        let idx = self.clauses.len();
+        self.watches[clause[0].code()].push(idx);
+        self.watches[clause[1].code()].push(idx);
+        self.clauses.push(clause);
+        idx
+    }
+
+    fn enqueue(&mut self, lit: Literal, reason: Option<usize>) {
+        self.values[lit.var()] = Some(!lit.is_neg());
+        self.levels[lit.var()] = self.decision_level();
+        self.reasons[lit.var()] = reason;
+        self.trail.push(lit);
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let limit = self.trail_limits[level];
+        for lit in self.trail.drain(limit..) {
+            self.phases[lit.var()] = !lit.is_neg();
+            self.values[lit.var()] = None;
+            self.reasons[lit.var()] = None;
+            self.heap
+                .push((self.activity[lit.var()].to_bits(), lit.var()));
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    // the clause that became false, if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_lit = !self.trail[self.propagated];
+            self.propagated += 1;
+
+            let watchers = mem::take(&mut self.watches[false_lit.code()]);
+            let mut kept: Vec<usize> = Vec::with_capacity(watchers.len());
+            let mut conflict = None;
+            for (pos, &idx) in watchers.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend(&watchers[pos..]);
+                    break;
+                }
+
+                // the false literal goes second
+                let clause = &mut self.clauses[idx];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.values[first.var()].map(|value| first.eval(value)) == Some(true) {
+                    kept.push(idx);
+                    continue;
+                }
+
+                let replacement = (2..clause.len()).find(|&k| {
+                    let lit = clause[k];
+                    self.values[lit.var()].map(|value| lit.eval(value)) != Some(false)
+                });
+                match replacement {
+                    Some(k) => {
+                        clause.swap(1, k);
+                        let watched = clause[1];
+                        self.watches[watched.code()].push(idx);
+                    }
+                    None => {
+                        kept.push(idx);
+                        if self.value(first) == Some(false) {
+                            conflict = Some(idx);
+                        } else {
+                            self.enqueue(first, Some(idx));
+                        }
+                    }
+                }
+            }
+            self.watches[false_lit.code()] = kept;
+
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.increment;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.increment *= 1e-100;
+            self.heap = (0..self.num_variables())
+                .filter(|&var| self.values[var].is_none())
+                .map(|var| (self.activity[var].to_bits(), var))
+                .collect();
+        }
+        if self.values[var].is_none() {
+            self.heap.push((self.activity[var].to_bits(), var));
+        }
+    }
+
+    // learnt clause with the asserting literal first and the backtrack level
+    fn analyze(&mut self, conflict: usize) -> (Clause, usize) {
+        let mut seen = vec![false; self.num_variables()];
+        let mut learnt: Clause = vec![Literal::new(Addr::new(0), false)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause_idx = conflict;
+        let mut implied: Option<Literal> = None;
+
+        loop {
+            let skip = implied.is_some() as usize;
+            for k in skip..self.clauses[clause_idx].len() {
+                let lit = self.clauses[clause_idx][k];
+                let var = lit.var();
+                if !seen[var] && self.levels[var] > 0 {
+                    seen[var] = true;
+                    self.bump(var);
+                    if self.levels[var] == self.decision_level() {
+                        pending += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            seen[lit.var()] = false;
+            pending -= 1;
+            implied = Some(lit);
+            if pending == 0 {
+                break;
+            }
+            clause_idx = self.reasons[lit.var()].unwrap();
+        }
+        learnt[0] = !implied.unwrap();
+
+        // the literal of the highest remaining level is watched second
+        let mut level = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len())
+                .max_by_key(|&k| self.levels[learnt[k].var()])
+                .unwrap();
+            learnt.swap(1, max);
+            level = self.levels[learnt[1].var()];
+        }
+        (learnt, level)
+    }
+
+    // assumptions implying the negation of the assumed literal, they form
+    // the core together with it
+    fn analyze_final(&mut self, lit: Literal) {
+        self.core = vec![lit];
+        if self.levels[lit.var()] == 0 {
+            return;
+        }
+
+        let mut seen = vec![false; self.num_variables()];
+        seen[lit.var()] = true;
+        for index in (self.trail_limits[0]..self.trail.len()).rev() {
+            let implied = self.trail[index];
+            if !seen[implied.var()] {
+                continue;
+            }
+            match self.reasons[implied.var()] {
+                None => self.core.push(implied),
+                Some(idx) => {
+                    for lit in self.clauses[idx][1..].iter() {
+                        if self.levels[lit.var()] > 0 {
+                            seen[lit.var()] = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn pick_branch(&mut self) -> Option<Literal> {
+        while let Some((activity, var)) = self.heap.pop() {
+            if self.values[var].is_none() && self.activity[var].to_bits() == activity {
+                self.num_decisions += 1;
+                return Some(Literal::new(Addr::new(var), !self.phases[var]));
+            }
+        }
+        None
+    }
+
+    // Some(true) on a model, Some(false) when unsatisfiable, None on restart
+    fn search(&mut self, assumptions: &[Literal], budget: usize) -> Option<bool> {
+        let mut num_conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.num_conflicts += 1;
+                num_conflicts += 1;
+                if self.decision_level() == 0 {
+                    self.unsatisfiable = true;
+                    return Some(false);
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let asserting = learnt[0];
+                    let idx = self.attach(learnt);
+                    self.enqueue(asserting, Some(idx));
+                }
+                self.increment /= ACTIVITY_DECAY;
+                continue;
+            }
+
+            if num_conflicts >= budget {
+                return None;
+            }
+
+            // assumptions are decided first, one level each
+            let mut decision = None;
+            while self.decision_level() < assumptions.len() {
+                let lit = assumptions[self.decision_level()];
+                match self.value(lit) {
+                    Some(true) => self.trail_limits.push(self.trail.len()),
+                    Some(false) => {
+                        self.analyze_final(lit);
+                        return Some(false);
+                    }
+                    None => {
+                        decision = Some(lit);
+                        break;
+                    }
+                }
+            }
+            let decision = match decision.or_else(|| self.pick_branch()) {
+                Some(lit) => lit,
+                None => return Some(true),
+            };
+            self.trail_limits.push(self.trail.len());
+            self.enqueue(decision, None);
+        }
+    }
+
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        self.solve_with(&[])
+    }
+
+    // a model where the assumptions hold, None if there is none; the learnt
+    // clauses are kept for the next calls
+    pub fn solve_with(&mut self, assumptions: &[Literal]) -> Option<Vec<bool>> {
+        self.core.clear();
+        if self.unsatisfiable {
+            return None;
+        }
+        while assumptions
+            .iter()
+            .any(|lit| lit.var() >= self.num_variables())
+        {
+            self.add_variable();
+        }
+
+        let mut restarts = 0;
+        loop {
+            self.backtrack(0);
+            match self.search(assumptions, luby(restarts) * RESTART_UNIT) {
+                Some(true) => {
+                    let model = self.values.iter().map(|value| value.unwrap()).collect();
+                    self.backtrack(0);
+                    return Some(model);
+                }
+                Some(false) => {
+                    self.backtrack(0);
+                    return None;
+                }
+                None => restarts += 1,
+            }
+        }
+    }
+
+    // subset of the assumptions of the last unsatisfiable call to solve_with
+    // that cannot hold together, empty when the clauses alone are unsatisfiable
+    pub fn core(&self) -> &[Literal] {
+        &self.core
+    }
+}
+
+pub fn solve(cnf: &Cnf) -> Option<Vec<bool>> {
+    Solver::from_cnf(cnf).solve()
+}
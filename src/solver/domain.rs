@@ -1,12 +1,27 @@
-use crate::tree::Addr;
+use crate::{
+    bignum::BigUint,
+    logic::{
+        propositional::{Cnf, Encoder, Literal, PropositionalTree},
+        Eval,
+    },
+    tree::Addr,
+};
+
+use super::{models::Models, sharpsat};
 
 pub trait Domain {
-    type Type;
+    type Type: Clone;
 
     fn iter(&self) -> impl Iterator<Item = Self::Type>;
     fn card(&self) -> usize;
+
+    // value at the given position of iter
+    fn value(&self, idx: usize) -> Self::Type {
+        self.iter().nth(idx).unwrap()
+    }
 }
 
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
 pub struct Boolean {}
 
 impl Domain for Boolean {
@@ -19,6 +34,10 @@ impl Domain for Boolean {
     fn card(&self) -> usize {
         2
     }
+
+    fn value(&self, idx: usize) -> Self::Type {
+        idx != 0
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -37,4 +56,240 @@ impl Domain for Integer {
     fn card(&self) -> usize {
         self.card
     }
+
+    fn value(&self, idx: usize) -> Self::Type {
+        idx
+    }
+}
+
+// integers from start up to end excluded
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Domain for Range {
+    type Type = i64;
+
+    fn iter(&self) -> impl Iterator<Item = Self::Type> {
+        self.start..self.end
+    }
+
+    fn card(&self) -> usize {
+        (self.end - self.start).max(0) as usize
+    }
+
+    fn value(&self, idx: usize) -> Self::Type {
+        self.start + idx as i64
+    }
+}
+
+// values are positions in the labels
+#[derive(PartialEq, Debug, Clone)]
+pub struct Categorical {
+    pub labels: Vec<String>,
+}
+
+impl Categorical {
+    pub fn label(&self, value: usize) -> &str {
+        &self.labels[value]
+    }
+}
+
+impl Domain for Categorical {
+    type Type = usize;
+
+    fn iter(&self) -> impl Iterator<Item = Self::Type> {
+        0..self.labels.len()
+    }
+
+    fn card(&self) -> usize {
+        self.labels.len()
+    }
+
+    fn value(&self, idx: usize) -> Self::Type {
+        idx
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DomainEncoding {
+    // one variable per value, exactly one of them true
+    #[default]
+    OneHot,
+    // the binary digits of the value, codes past the domain excluded
+    Log,
+}
+
+// boolean variables standing for multi-valued ones, the bits of each
+// variable are allocated consecutively from the first one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lowering {
+    pub encoding: DomainEncoding,
+    cards: Vec<usize>,
+    bits: Vec<Vec<Addr>>,
+}
+
+fn log_width(card: usize) -> usize {
+    match card {
+        0 | 1 => 0,
+        _ => (usize::BITS - (card - 1).leading_zeros()) as usize,
+    }
+}
+
+impl Lowering {
+    pub fn new<D: Domain>(domains: &[D], encoding: DomainEncoding) -> Self {
+        let mut next = 0;
+        let cards: Vec<usize> = domains.iter().map(|domain| domain.card()).collect();
+        let bits = cards
+            .iter()
+            .map(|&card| {
+                let width = match encoding {
+                    DomainEncoding::OneHot => card,
+                    DomainEncoding::Log => log_width(card),
+                };
+                next += width;
+                (next - width..next).map(Addr::new).collect()
+            })
+            .collect();
+        Lowering {
+            encoding,
+            cards,
+            bits,
+        }
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.bits.iter().map(|bits| bits.len()).sum()
+    }
+
+    pub fn bits(&self, var: usize) -> &[Addr] {
+        &self.bits[var]
+    }
+
+    // conjunction of literals meaning that the variable takes the value,
+    // assuming the constraints of the encoding hold
+    pub fn literals(&self, var: usize, value: usize) -> Vec<Literal> {
+        match self.encoding {
+            DomainEncoding::OneHot => vec![Literal::new(self.bits[var][value], false)],
+            DomainEncoding::Log => self.bits[var]
+                .iter()
+                .enumerate()
+                .map(|(i, &id)| Literal::new(id, value & (1 << i) == 0))
+                .collect(),
+        }
+    }
+
+    // clauses keeping the bits of every variable to a valid value,
+    // auxiliary variables are anonymous ones determined by the bits
+    pub fn add_constraints(&self, cnf: &mut Cnf) {
+        cnf.num_variables = cnf.num_variables.max(self.num_variables());
+        let mut vars = PropositionalTree::default();
+        for (var, &card) in self.cards.iter().enumerate() {
+            if card == 0 {
+                cnf.add_clause(&[]);
+                continue;
+            }
+            match self.encoding {
+                DomainEncoding::OneHot => {
+                    let lits: Vec<Literal> = (0..card)
+                        .map(|value| Literal::new(self.bits[var][value], false))
+                        .collect();
+                    let encoder = Encoder {
+                        count_preserving: true,
+                        ..Default::default()
+                    };
+                    encoder.exactly_k(cnf, &mut vars, &lits, 1);
+                }
+                DomainEncoding::Log => {
+                    for value in card..1 << self.bits[var].len() {
+                        let clause: Vec<Literal> = self
+                            .literals(var, value)
+                            .into_iter()
+                            .map(|lit| !lit)
+                            .collect();
+                        cnf.add_clause(&clause);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn encode(&self, values: &[usize]) -> Vec<bool> {
+        let mut assignment = vec![false; self.num_variables()];
+        for (var, &value) in values.iter().enumerate() {
+            for lit in self.literals(var, value) {
+                assignment[lit.var()] = !lit.is_neg();
+            }
+        }
+        assignment
+    }
+
+    // None when the bits of a variable are not a valid value
+    pub fn decode(&self, assignment: &[bool]) -> Option<Vec<usize>> {
+        self.bits
+            .iter()
+            .zip(self.cards.iter())
+            .map(|(bits, &card)| {
+                let value = match self.encoding {
+                    DomainEncoding::OneHot => {
+                        let mut values = bits
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, id)| assignment[id.addr()])
+                            .map(|(i, _)| i);
+                        match (values.next(), values.next()) {
+                            (Some(value), None) => value,
+                            _ => return None,
+                        }
+                    }
+                    DomainEncoding::Log => bits
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, id)| assignment[id.addr()])
+                        .map(|(i, _)| 1 << i)
+                        .sum(),
+                };
+                (value < card).then_some(value)
+            })
+            .collect()
+    }
+
+    // the expression over the bits seen as one over the multi-valued variables
+    pub fn lower<'a, T>(&'a self, expr: &'a T) -> Lowered<'a, T> {
+        Lowered {
+            lowering: self,
+            expr,
+        }
+    }
+
+    // models of clauses over the bits, as values of the multi-valued variables
+    pub fn models(&self, cnf: &Cnf) -> impl Iterator<Item = Vec<usize>> + '_ {
+        let mut constrained = cnf.clone();
+        self.add_constraints(&mut constrained);
+        let projection: Vec<Addr> = (0..self.num_variables()).map(Addr::new).collect();
+        Models::projected(&constrained, &projection)
+            .map(|assignment| self.decode(&assignment).unwrap())
+    }
+
+    // variables of the CNF past the bits have to be determined by the bits
+    pub fn count(&self, cnf: &Cnf) -> BigUint {
+        let mut constrained = cnf.clone();
+        self.add_constraints(&mut constrained);
+        sharpsat::count(&constrained)
+    }
+}
+
+pub struct Lowered<'a, T> {
+    lowering: &'a Lowering,
+    expr: &'a T,
+}
+
+impl<'a, T: Eval<bool, Output = bool>> Eval<usize> for Lowered<'a, T> {
+    type Output = bool;
+
+    fn eval(&self, assignment: &Vec<usize>) -> Self::Output {
+        self.expr.eval(&self.lowering.encode(assignment))
+    }
 }
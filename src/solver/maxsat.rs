@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::{
+    logic::propositional::{Clause, Cnf, Literal},
+    tree::Addr,
+};
+
+use super::cdcl::Solver;
+
+// hard clauses and weighted soft clauses, the cost of an assignment is the
+// total weight of the soft clauses it falsifies
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wcnf {
+    pub num_variables: usize,
+    pub hard: Vec<Clause>,
+    pub soft: Vec<(Clause, u64)>,
+}
+
+impl Wcnf {
+    pub fn new(num_variables: usize) -> Self {
+        Wcnf {
+            num_variables,
+            ..Default::default()
+        }
+    }
+
+    // every clause of the CNF is hard
+    pub fn from_cnf(cnf: &Cnf) -> Self {
+        Wcnf {
+            num_variables: cnf.num_variables,
+            hard: cnf.clauses.clone(),
+            soft: Default::default(),
+        }
+    }
+
+    fn grow(&mut self, clause: &[Literal]) {
+        for lit in clause {
+            self.num_variables = self.num_variables.max(lit.var() + 1);
+        }
+    }
+
+    pub fn add_hard(&mut self, clause: &[Literal]) {
+        self.grow(clause);
+        self.hard.push(clause.to_vec());
+    }
+
+    // soft clauses of weight zero never change the cost and are dropped
+    pub fn add_soft(&mut self, clause: &[Literal], weight: u64) {
+        self.grow(clause);
+        if weight > 0 {
+            self.soft.push((clause.to_vec(), weight));
+        }
+    }
+
+    pub fn is_feasible(&self, assignment: &[bool]) -> bool {
+        self.hard.iter().all(|clause| satisfies(clause, assignment))
+    }
+
+    pub fn cost(&self, assignment: &[bool]) -> u64 {
+        self.soft
+            .iter()
+            .filter(|(clause, _)| !satisfies(clause, assignment))
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+}
+
+fn satisfies(clause: &[Literal], assignment: &[bool]) -> bool {
+    clause.iter().any(|lit| lit.eval(assignment[lit.var()]))
+}
+
+// soft clause extended with relaxation variables, falsified only when its
+// blocking variable is true
+struct Soft {
+    clause: Clause,
+    weight: u64,
+    blocking: Addr,
+}
+
+fn add_soft(solver: &mut Solver, softs: &mut Vec<Soft>, clause: Clause, weight: u64) {
+    let blocking = solver.add_variable();
+    let mut relaxed = clause.clone();
+    relaxed.push(Literal::new(blocking, false));
+    solver.add_clause(&relaxed);
+    softs.push(Soft {
+        clause,
+        weight,
+        blocking,
+    });
+}
+
+// at most one of the literals is true, sequential encoding
+fn at_most_one(solver: &mut Solver, lits: &[Literal]) {
+    if lits.len() <= 4 {
+        for (i, &a) in lits.iter().enumerate() {
+            for &b in lits[i + 1..].iter() {
+                solver.add_clause(&[!a, !b]);
+            }
+        }
+        return;
+    }
+
+    // prefix[i] is true when one of the first i + 1 literals is true
+    let mut prefix = Literal::new(solver.add_variable(), false);
+    solver.add_clause(&[!lits[0], prefix]);
+    for &lit in lits[1..].iter() {
+        solver.add_clause(&[!lit, !prefix]);
+        let next = Literal::new(solver.add_variable(), false);
+        solver.add_clause(&[!prefix, next]);
+        solver.add_clause(&[!lit, next]);
+        prefix = next;
+    }
+}
+
+// optimal assignment and its cost by weighted Fu-Malik (WPM1): each
+// unsatisfiable core of soft clauses is relaxed by exactly one new
+// variable per clause, the weights being split at the smallest weight of
+// the core
+pub fn solve(wcnf: &Wcnf) -> Result<(Vec<bool>, u64), &'static str> {
+    let mut solver = Solver::new(wcnf.num_variables);
+    for clause in wcnf.hard.iter() {
+        if !solver.add_clause(clause) {
+            return Err("Hard clauses are unsatisfiable");
+        }
+    }
+
+    let mut softs: Vec<Soft> = Default::default();
+    for (clause, weight) in wcnf.soft.iter() {
+        add_soft(&mut solver, &mut softs, clause.clone(), *weight);
+    }
+
+    loop {
+        let assumptions: Vec<Literal> = softs
+            .iter()
+            .filter(|soft| soft.weight > 0)
+            .map(|soft| Literal::new(soft.blocking, true))
+            .collect();
+        if let Some(mut model) = solver.solve_with(&assumptions) {
+            model.truncate(wcnf.num_variables);
+            let cost = wcnf.cost(&model);
+            return Ok((model, cost));
+        }
+        if solver.core().is_empty() {
+            return Err("Hard clauses are unsatisfiable");
+        }
+
+        let owners: HashMap<Addr, usize> = softs
+            .iter()
+            .enumerate()
+            .filter(|(_, soft)| soft.weight > 0)
+            .map(|(idx, soft)| (soft.blocking, idx))
+            .collect();
+        let mut core: Vec<usize> = solver.core().iter().map(|lit| owners[&lit.id()]).collect();
+        core.sort();
+        core.dedup();
+        let min_weight = core.iter().map(|&idx| softs[idx].weight).min().unwrap();
+
+        // the part of each clause above the minimum weight stays as it is,
+        // the rest is relaxed
+        let mut relaxations: Vec<Literal> = Default::default();
+        for idx in core {
+            let remaining = softs[idx].weight - min_weight;
+            softs[idx].weight = 0;
+            let clause = softs[idx].clause.clone();
+            if remaining > 0 {
+                add_soft(&mut solver, &mut softs, clause.clone(), remaining);
+            }
+
+            let relaxation = Literal::new(solver.add_variable(), false);
+            relaxations.push(relaxation);
+            let mut relaxed = clause;
+            relaxed.push(relaxation);
+            add_soft(&mut solver, &mut softs, relaxed, min_weight);
+        }
+        at_most_one(&mut solver, &relaxations);
+        solver.add_clause(&relaxations);
+    }
+}
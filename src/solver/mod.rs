@@ -1,2 +1,11 @@
+pub mod approxmc;
+pub mod cdcl;
 pub mod domain;
+pub mod models;
+pub mod maxsat;
 pub mod naive;
+pub mod sampling;
+pub mod sharpsat;
+
+#[cfg(test)]
+mod tests;
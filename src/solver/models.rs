@@ -0,0 +1,179 @@
+use crate::{
+    logic::{
+        propositional::{tseitin, Clause, Cnf, Literal, PropositionalTree},
+        Eval,
+    },
+    tree::{Addr, Mapping},
+};
+
+use super::cdcl::Solver;
+
+// formula used to widen a model into a cube of don't cares
+#[derive(Debug, Clone)]
+enum Lifting {
+    Clauses {
+        clauses: Vec<Clause>,
+        occurrences: Vec<Vec<usize>>,
+    },
+    Tree(PropositionalTree),
+}
+
+// distinct models projected on a set of variables, each one excluded by a
+// blocking clause once found
+#[derive(Debug, Clone)]
+pub struct Models {
+    solver: Solver,
+    projection: Vec<Addr>,
+    positions: Vec<Option<usize>>,
+    lifting: Lifting,
+    // cubes already returned, the next ones are kept disjoint from them
+    blocked: Vec<Vec<Option<bool>>>,
+}
+
+impl Models {
+    pub fn new(cnf: &Cnf) -> Self {
+        let projection: Vec<Addr> = (0..cnf.num_variables).map(Addr::new).collect();
+        Self::projected(cnf, &projection)
+    }
+
+    pub fn projected(cnf: &Cnf, projection: &[Addr]) -> Self {
+        let mut occurrences: Vec<Vec<usize>> = vec![Default::default(); cnf.num_variables];
+        for (idx, clause) in cnf.clauses.iter().enumerate() {
+            for lit in clause.iter() {
+                occurrences[lit.var()].push(idx);
+            }
+        }
+        let lifting = Lifting::Clauses {
+            clauses: cnf.clauses.clone(),
+            occurrences,
+        };
+        Self::with_lifting(Solver::from_cnf(cnf), projection, lifting)
+    }
+
+    // models over the variables of the tree, solved through the Tseitin transform
+    pub fn from_propositional(tree: &PropositionalTree) -> Self {
+        let projection: Vec<Addr> = (0..tree.num_named()).map(Addr::new).collect();
+        let solver = Solver::from_cnf(&tseitin(tree));
+        Self::with_lifting(solver, &projection, Lifting::Tree(tree.clone()))
+    }
+
+    fn with_lifting(mut solver: Solver, projection: &[Addr], lifting: Lifting) -> Self {
+        let mut positions: Vec<Option<usize>> = vec![None; solver.num_variables()];
+        for (pos, id) in projection.iter().enumerate() {
+            while solver.num_variables() <= id.addr() {
+                solver.add_variable();
+                positions.push(None);
+            }
+            positions[id.addr()] = Some(pos);
+        }
+        Models {
+            solver,
+            projection: projection.to_vec(),
+            positions,
+            lifting,
+            blocked: Default::default(),
+        }
+    }
+
+    pub fn projection(&self) -> &[Addr] {
+        &self.projection
+    }
+
+    // partial models instead, every completion of a cube is a model
+    pub fn cubes(self) -> Cubes {
+        Cubes { models: self }
+    }
+
+    // the clause excluding every completion of the cube
+    fn block(&mut self, cube: &[Option<bool>]) {
+        let clause: Vec<Literal> = self
+            .projection
+            .iter()
+            .zip(cube.iter())
+            .filter_map(|(&id, value)| value.map(|value| Literal::new(id, value)))
+            .collect();
+        self.solver.add_clause(&clause);
+    }
+
+    fn disjoint(&self, cube: &[Option<bool>]) -> bool {
+        self.blocked.iter().all(|other| {
+            cube.iter()
+                .zip(other.iter())
+                .any(|(a, b)| matches!((a, b), (Some(a), Some(b)) if a != b))
+        })
+    }
+
+    // greedily drops the variables the formula and the previous cubes do not
+    // need, the others keep the values of the model
+    fn lift(&self, model: &[bool]) -> Vec<Option<bool>> {
+        let mut cube: Vec<Option<bool>> = self
+            .projection
+            .iter()
+            .map(|id| Some(model[id.addr()]))
+            .collect();
+        match &self.lifting {
+            Lifting::Clauses {
+                clauses,
+                occurrences,
+            } => {
+                for pos in 0..cube.len() {
+                    let var = self.projection[pos].addr();
+                    cube[pos] = None;
+                    let value = |lit: &Literal| match self.positions[lit.var()] {
+                        Some(pos) => cube[pos].map(|value| lit.eval(value)),
+                        None => Some(lit.eval(model[lit.var()])),
+                    };
+                    let needed = occurrences.get(var).is_some_and(|occurrences| {
+                        occurrences
+                            .iter()
+                            .any(|&idx| !clauses[idx].iter().any(|lit| value(lit) == Some(true)))
+                    });
+                    if needed || !self.disjoint(&cube) {
+                        cube[pos] = Some(model[var]);
+                    }
+                }
+            }
+            Lifting::Tree(tree) => {
+                for pos in 0..cube.len() {
+                    let value = cube[pos].take();
+                    if tree.eval(&cube) != Some(true) || !self.disjoint(&cube) {
+                        cube[pos] = value;
+                    }
+                }
+            }
+        }
+        cube
+    }
+}
+
+impl Iterator for Models {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let model = self.solver.solve()?;
+        let projected: Vec<bool> = self.projection.iter().map(|id| model[id.addr()]).collect();
+        let cube: Vec<Option<bool>> = projected.iter().map(|&value| Some(value)).collect();
+        self.block(&cube);
+        Some(projected)
+    }
+}
+
+pub struct Cubes {
+    models: Models,
+}
+
+impl Iterator for Cubes {
+    type Item = Vec<Option<bool>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let model = self.models.solver.solve()?;
+        let cube = self.models.lift(&model);
+        self.models.block(&cube);
+        self.models.blocked.push(cube.clone());
+        Some(cube)
+    }
+}
+
+pub fn enumerate(tree: &PropositionalTree) -> Models {
+    Models::from_propositional(tree)
+}
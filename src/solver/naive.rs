@@ -1,30 +1,53 @@
-use crate::{logic::semantic::Eval, tree::Mapping};
+use crate::{bignum::BigUint, logic::semantic::Eval, tree::Mapping};
 
-pub struct Enumerate<'a, T: Eval<bool>> {
+use super::domain::{Boolean, Domain};
+
+pub struct Enumerate<'a, T, D: Domain = Boolean> {
     expr: &'a T,
-    num_variables: usize,
-    current_solution: usize,
+    domains: Vec<D>,
+    // position of the value of each variable in its domain
+    current_solution: Option<Vec<usize>>,
 }
 
-impl<'a, T: Eval<bool>> Enumerate<'a, T> {
-    pub fn domain_size(&self) -> usize {
-        1 << self.num_variables
+impl<'a, T, D: Domain> Enumerate<'a, T, D> {
+    pub fn domain_size(&self) -> BigUint {
+        let mut size = BigUint::from(1u64);
+        for domain in self.domains.iter() {
+            size *= &BigUint::from(domain.card() as u64);
+        }
+        size
     }
 }
 
-impl<'a, T: Eval<bool, Output = bool>> Iterator for Enumerate<'a, T> {
-    type Item = Vec<bool>;
+// mixed radix counter with the first variable as least significant digit, false on overflow
+fn increment<D: Domain>(positions: &mut [usize], domains: &[D]) -> bool {
+    for (position, domain) in positions.iter_mut().zip(domains) {
+        *position += 1;
+        if *position < domain.card() {
+            return true;
+        }
+        *position = 0;
+    }
+    false
+}
+
+impl<'a, T: Eval<D::Type, Output = bool>, D: Domain> Iterator for Enumerate<'a, T, D> {
+    type Item = Vec<D::Type>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.current_solution < self.domain_size() {
-            let assignment: Vec<bool> = (0..self.num_variables)
-                .map(|x| ((1 << x) & self.current_solution) != 0)
+        while let Some(positions) = self.current_solution.as_mut() {
+            let candidate: Vec<D::Type> = positions
+                .iter()
+                .zip(self.domains.iter())
+                .map(|(&position, domain)| domain.value(position))
                 .collect();
 
-            self.current_solution += 1;
+            if !increment(positions, &self.domains) {
+                self.current_solution = None;
+            }
 
-            if self.expr.eval(&assignment) {
-                return Some(assignment);
+            if self.expr.eval(&candidate) {
+                return Some(candidate);
             }
         }
         None
@@ -32,11 +55,34 @@ impl<'a, T: Eval<bool, Output = bool>> Iterator for Enumerate<'a, T> {
 }
 
 pub fn enumerate<'a, T: Mapping + Eval<bool>>(expr: &'a T) -> Enumerate<'a, T> {
-    assert!(expr.num_named() <= (usize::BITS - 1) as usize);
+    enumerate_domains(expr, vec![Boolean {}; expr.num_named()])
+}
 
+// assignments of the variables to values of their domains satisfying the expression
+pub fn enumerate_domains<'a, T: Eval<D::Type>, D: Domain>(
+    expr: &'a T,
+    domains: Vec<D>,
+) -> Enumerate<'a, T, D> {
+    let empty = domains.iter().any(|domain| domain.card() == 0);
     Enumerate {
-        expr: expr,
-        num_variables: expr.num_named(),
-        current_solution: 0,
+        expr,
+        current_solution: (!empty).then(|| vec![0; domains.len()]),
+        domains,
+    }
+}
+
+pub fn count<T: Mapping + Eval<bool, Output = bool>>(expr: &T) -> BigUint {
+    count_domains(expr, vec![Boolean {}; expr.num_named()])
+}
+
+pub fn count_domains<T: Eval<D::Type, Output = bool>, D: Domain>(
+    expr: &T,
+    domains: Vec<D>,
+) -> BigUint {
+    let mut count = BigUint::default();
+    let one = BigUint::from(1u64);
+    for _ in enumerate_domains(expr, domains) {
+        count += &one;
     }
+    count
 }
@@ -0,0 +1,148 @@
+use crate::{
+    logic::{
+        circuit::{cnf_to_ddnnf, sample_conditional},
+        propositional::{tseitin, Cnf, PropositionalTree},
+    },
+    random::Rng,
+    tree::{Addr, Mapping},
+};
+
+use super::{
+    approxmc::{bounded_models, random_xors, ApproxMc},
+    cdcl::Solver,
+};
+
+// exactly uniform models of the CNF, sampled proportionally to the model
+// counts of a compiled d-DNNF
+pub fn sample_exact(cnf: &Cnf, rng: &mut Rng, n: usize) -> Result<Vec<Vec<bool>>, &'static str> {
+    let circuit = cnf_to_ddnnf(cnf)?;
+    let evidence = vec![None; cnf.num_variables];
+    sample_conditional(&circuit, &evidence, rng, n)
+}
+
+// auxiliary variables of the Tseitin transform are determined by the
+// variables of the tree, so the projection stays uniform
+pub fn sample_exact_propositional(
+    tree: &PropositionalTree,
+    rng: &mut Rng,
+    n: usize,
+) -> Result<Vec<Vec<bool>>, &'static str> {
+    let mut samples = sample_exact(&tseitin(tree), rng, n)?;
+    for sample in samples.iter_mut() {
+        sample.truncate(tree.num_named());
+    }
+    Ok(samples)
+}
+
+// near-uniform sampling by random XOR hashing, every model is drawn with a
+// probability within a factor 1 + epsilon of uniform, epsilon above 1.71
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniGen {
+    pub epsilon: f64,
+    // the model count is estimated with these settings
+    pub counter: ApproxMc,
+    // number of attempts per sample before giving up
+    pub max_attempts: usize,
+}
+
+impl Default for UniGen {
+    fn default() -> Self {
+        UniGen {
+            epsilon: 3.0,
+            counter: Default::default(),
+            max_attempts: 100,
+        }
+    }
+}
+
+impl UniGen {
+    // kappa such that epsilon = (1 + kappa)(2.23 + 0.48 / (1 - kappa)^2) - 1
+    fn kappa(&self) -> Result<f64, &'static str> {
+        let epsilon = |kappa: f64| (1.0 + kappa) * (2.23 + 0.48 / (1.0 - kappa).powi(2)) - 1.0;
+        if self.epsilon.is_nan() || self.epsilon <= epsilon(0.0) {
+            return Err("Tolerance has to be above 1.71");
+        }
+
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..64 {
+            let middle = (low + high) / 2.0;
+            if epsilon(middle) < self.epsilon {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(low)
+    }
+
+    // lower bound, expected and upper bound on the size of an accepted cell
+    fn thresholds(&self) -> Result<(usize, f64, usize), &'static str> {
+        let kappa = self.kappa()?;
+        let pivot = (4.03 * (1.0 + 1.0 / kappa).powi(2)).ceil();
+        let scale = std::f64::consts::SQRT_2 * (1.0 + kappa);
+        Ok((
+            (pivot / scale).ceil() as usize,
+            pivot,
+            (1.0 + scale * pivot) as usize,
+        ))
+    }
+
+    // the projection should hold the independent variables, samples are
+    // values of the projected variables
+    pub fn sample(
+        &self,
+        cnf: &Cnf,
+        projection: &[Addr],
+        rng: &mut Rng,
+        n: usize,
+    ) -> Result<Vec<Vec<bool>>, &'static str> {
+        let (low, pivot, high) = self.thresholds()?;
+        let base = Solver::from_cnf(cnf);
+
+        // few models, pick uniformly among all of them
+        let models = bounded_models(base.clone(), projection, high + 1);
+        if models.is_empty() {
+            return Err("Formula is unsatisfiable");
+        }
+        if models.len() <= high {
+            return Ok((0..n)
+                .map(|_| models[rng.below(models.len())].clone())
+                .collect());
+        }
+
+        // number of XORs expected to leave about pivot models in a cell
+        let count = self.counter.count(cnf, projection)?.estimate.to_f64();
+        let hashes = (count.log2() + 1.8f64.log2() - pivot.log2())
+            .ceil()
+            .max(0.0) as usize;
+
+        let mut samples: Vec<Vec<bool>> = Default::default();
+        'samples: for _ in 0..n {
+            for _ in 0..self.max_attempts {
+                for m in hashes.saturating_sub(3)..=hashes {
+                    let mut solver = base.clone();
+                    for (vars, parity) in random_xors(rng, projection, m) {
+                        solver.add_xor(&vars, parity);
+                    }
+                    let cell = bounded_models(solver, projection, high + 1);
+                    if low <= cell.len() && cell.len() <= high {
+                        samples.push(cell[rng.below(cell.len())].clone());
+                        continue 'samples;
+                    }
+                }
+            }
+            return Err("No cell of acceptable size was found");
+        }
+        Ok(samples)
+    }
+
+    pub fn sample_propositional(
+        &self,
+        tree: &PropositionalTree,
+        rng: &mut Rng,
+        n: usize,
+    ) -> Result<Vec<Vec<bool>>, &'static str> {
+        let projection: Vec<Addr> = (0..tree.num_named()).map(Addr::new).collect();
+        self.sample(&tseitin(tree), &projection, rng, n)
+    }
+}
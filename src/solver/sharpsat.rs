@@ -0,0 +1,68 @@
+use crate::{
+    bignum::BigUint,
+    logic::{
+        propositional::{tseitin, Cnf, ComponentSearch, Decomposition, Literal, PropositionalTree},
+        Semiring,
+    },
+    tree::{Addr, Mapping},
+};
+
+// the count of a formula is the product of the counts of its independent
+// components, and the sum over the two values of a decision
+struct Counter<S: Semiring, F: Fn(Addr, bool) -> S> {
+    leaf: F,
+}
+
+impl<S: Semiring, F: Fn(Addr, bool) -> S> Decomposition for Counter<S, F> {
+    type Value = S;
+
+    fn literal(&mut self, lit: Literal) -> S {
+        (self.leaf)(lit.id(), lit.is_neg())
+    }
+
+    fn free(&mut self, var: usize) -> S {
+        let id = Addr::new(var);
+        (self.leaf)(id, false).plus(&(self.leaf)(id, true))
+    }
+
+    fn product(&mut self, parts: Vec<S>) -> S {
+        parts.iter().fold(S::one(), |count, part| count.times(part))
+    }
+
+    fn decision(&mut self, positive: S, negative: S) -> S {
+        positive.plus(&negative)
+    }
+}
+
+// sum over the models of the product of their literal weights, leaf is called
+// with the variable id and whether the literal is negated
+pub fn weighted_count<S: Semiring, F: Fn(Addr, bool) -> S>(cnf: &Cnf, leaf: F) -> S {
+    ComponentSearch::new(Counter { leaf })
+        .search(cnf)
+        .unwrap_or_else(S::zero)
+}
+
+// number of models over all the variables of the CNF
+pub fn count(cnf: &Cnf) -> BigUint {
+    weighted_count(cnf, |_, _| BigUint::from(1u64))
+}
+
+// the auxiliary variables of the Tseitin transform are determined by the
+// original ones, so counting them with weight one keeps the count
+pub fn count_propositional(tree: &PropositionalTree) -> BigUint {
+    count(&tseitin(tree))
+}
+
+pub fn weighted_count_propositional<S: Semiring, F: Fn(Addr, bool) -> S>(
+    tree: &PropositionalTree,
+    leaf: F,
+) -> S {
+    let num_named = tree.num_named();
+    weighted_count(&tseitin(tree), |id, neg| {
+        if id.addr() < num_named {
+            leaf(id, neg)
+        } else {
+            S::one()
+        }
+    })
+}
@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+
+use crate::{
+    bignum::BigUint,
+    logic::{
+        propositional::{tseitin, Cnf, Literal, PMut, PropositionalTree},
+        Eval,
+    },
+    random::Rng,
+    tree::{Addr, Mapping},
+};
+
+use super::{
+    approxmc::ApproxMc,
+    cdcl::{solve, Solver},
+    domain::{Categorical, DomainEncoding, Integer, Lowering, Range},
+    maxsat::{self, Wcnf},
+    models::{enumerate, Models},
+    naive,
+    sampling::{sample_exact, sample_exact_propositional, UniGen},
+    sharpsat,
+};
+
+fn random_cnf(rng: &mut Rng, num_variables: usize, num_clauses: usize) -> Cnf {
+    let mut cnf = Cnf::new(num_variables);
+    for _ in 0..num_clauses {
+        let clause: Vec<Literal> = (0..3)
+            .map(|_| Literal::new(Addr::new(rng.below(num_variables)), rng.next_bool()))
+            .collect();
+        cnf.add_clause(&clause);
+    }
+    cnf
+}
+
+// n + 1 pigeons in n holes
+fn pigeonhole(holes: usize) -> Cnf {
+    let var =
+        |pigeon: usize, hole: usize, neg: bool| Literal::new(Addr::new(pigeon * holes + hole), neg);
+    let mut cnf = Cnf::new((holes + 1) * holes);
+    for pigeon in 0..=holes {
+        let clause: Vec<Literal> = (0..holes).map(|hole| var(pigeon, hole, false)).collect();
+        cnf.add_clause(&clause);
+    }
+    for hole in 0..holes {
+        for a in 0..=holes {
+            for b in a + 1..=holes {
+                cnf.add_clause(&[var(a, hole, true), var(b, hole, true)]);
+            }
+        }
+    }
+    cnf
+}
+
+#[test]
+fn cdcl() {
+    let mut rng = Rng::new(17);
+    for round in 0..200 {
+        let num_variables = 3 + round % 10;
+        let num_clauses = rng.below(6 * num_variables);
+        let cnf = random_cnf(&mut rng, num_variables, num_clauses);
+        let satisfiable = (0..1usize << num_variables).any(|x| {
+            let assignment: Vec<bool> = (0..num_variables).map(|i| x & (1 << i) != 0).collect();
+            cnf.eval(&assignment)
+        });
+        match solve(&cnf) {
+            Some(model) => assert!(cnf.eval(&model)),
+            None => assert!(!satisfiable),
+        }
+    }
+
+    assert_eq!(solve(&pigeonhole(5)), None);
+
+    // under-constrained random 3-SAT, far beyond enumeration
+    let cnf = random_cnf(&mut rng, 300, 900);
+    let model = solve(&cnf).unwrap();
+    assert!(cnf.eval(&model));
+}
+
+#[test]
+fn assumptions() {
+    // x0 → x1 → x2 → x3
+    let mut solver = Solver::new(4);
+    for i in 0..3 {
+        solver.add_clause(&[
+            Literal::new(Addr::new(i), true),
+            Literal::new(Addr::new(i + 1), false),
+        ]);
+    }
+
+    let x = |i: usize, neg: bool| Literal::new(Addr::new(i), neg);
+    let model = solver.solve_with(&[x(0, false)]).unwrap();
+    assert_eq!(model, vec![true; 4]);
+    assert_eq!(solver.solve_with(&[x(0, false), x(3, true)]), None);
+    let mut core = solver.core().to_vec();
+    core.sort();
+    assert_eq!(core, vec![x(0, false), x(3, true)]);
+    assert_eq!(solver.solve_with(&[x(2, true), x(1, false), x(3, false)]), None);
+    assert_eq!(solver.core().len(), 2);
+    let model = solver.solve_with(&[x(3, true)]).unwrap();
+    assert_eq!(model, vec![false; 4]);
+
+    // still satisfiable after a failed call, until a clause closes it
+    assert!(solver.solve().is_some());
+    assert!(solver.add_clause(&[x(1, false)]));
+    assert_eq!(solver.solve_with(&[x(3, true)]), None);
+    assert!(!solver.add_clause(&[x(2, true)]));
+    assert_eq!(solver.solve(), None);
+}
+
+#[test]
+fn tseitin_transform() {
+    let tree = PropositionalTree::build(|builder| {
+        builder.or(
+            |left| {
+                left.and(
+                    |left| left.var("A"),
+                    |right| right.not(|inner| inner.var("B")),
+                )
+            },
+            |right| {
+                right.not(|inner| {
+                    inner.or(
+                        |left| left.var("C"),
+                        |right| right.and(|left| left.var("A"), |right| right.var("B")),
+                    )
+                })
+            },
+        )
+    });
+
+    let cnf = tseitin(&tree);
+    assert_eq!(cnf.num_variables, 3 + 4);
+    let mut count = 0;
+    for x in 0..1usize << cnf.num_variables {
+        let assignment: Vec<bool> = (0..cnf.num_variables).map(|i| x & (1 << i) != 0).collect();
+        if cnf.eval(&assignment) {
+            assert!(tree.eval(&assignment[..3].to_vec()));
+            count += 1;
+        }
+    }
+    assert_eq!(count, naive::count(&tree).to_u128().unwrap());
+
+    let model = solve(&cnf).unwrap();
+    assert!(tree.eval(&model[..3].to_vec()));
+}
+
+fn completions(cube: &[Option<bool>]) -> Vec<Vec<bool>> {
+    let mut completions = vec![Vec::new()];
+    for value in cube {
+        completions = completions
+            .into_iter()
+            .flat_map(|prefix| {
+                let values = match value {
+                    Some(value) => vec![*value],
+                    None => vec![false, true],
+                };
+                values.into_iter().map(move |value| {
+                    let mut completion = prefix.clone();
+                    completion.push(value);
+                    completion
+                })
+            })
+            .collect();
+    }
+    completions
+}
+
+#[test]
+fn all_solutions() {
+    let tree = PropositionalTree::build(|builder| {
+        builder.or(
+            |left| {
+                left.and(
+                    |left| left.var("A"),
+                    |right| right.not(|inner| inner.var("B")),
+                )
+            },
+            |right| right.or(|left| left.var("C"), |right| right.var("D")),
+        )
+    });
+    let mut expected: Vec<Vec<bool>> = naive::enumerate(&tree).collect();
+    expected.sort();
+
+    let mut models: Vec<Vec<bool>> = enumerate(&tree).collect();
+    models.sort();
+    assert_eq!(models, expected);
+
+    // projection on the original variables of the Tseitin transform
+    let cnf = tseitin(&tree);
+    let projection: Vec<Addr> = (0..4).map(Addr::new).collect();
+    let mut models: Vec<Vec<bool>> = Models::projected(&cnf, &projection).collect();
+    models.sort();
+    assert_eq!(models, expected);
+
+    // cubes are disjoint and cover the models
+    for cubes in [
+        enumerate(&tree).cubes().collect::<Vec<_>>(),
+        Models::projected(&cnf, &projection).cubes().collect(),
+    ] {
+        let mut covered: Vec<Vec<bool>> = cubes.iter().flat_map(|cube| completions(cube)).collect();
+        covered.sort();
+        assert_eq!(covered, expected);
+    }
+    assert!(enumerate(&tree).cubes().count() < expected.len());
+
+    let mut rng = Rng::new(23);
+    for _ in 0..20 {
+        let cnf = random_cnf(&mut rng, 8, 12);
+        let count = (0..256usize)
+            .filter(|x| cnf.eval(&(0..8).map(|i| x & (1 << i) != 0).collect::<Vec<bool>>()))
+            .count();
+        assert_eq!(Models::new(&cnf).count(), count);
+        let cubes: Vec<Vec<Option<bool>>> = Models::new(&cnf).cubes().collect();
+        for cube in cubes.iter() {
+            assert!(completions(cube).iter().all(|model| cnf.eval(model)));
+        }
+        let covered: usize = cubes
+            .iter()
+            .map(|cube| 1 << cube.iter().filter(|value| value.is_none()).count())
+            .sum();
+        assert_eq!(covered, count);
+    }
+
+    // 100 chained equivalences have two models
+    let mut chain: PropositionalTree = Default::default();
+    for _ in 0..100 {
+        chain.add_anon();
+    }
+    chain.builder(|builder| {
+        builder.conjunction(&mut (0..99), |builder, i| {
+            builder.or(
+                |left| {
+                    left.and(
+                        |left| left.var(Addr::new(i)),
+                        |right| right.var(Addr::new(i + 1)),
+                    )
+                },
+                |right| {
+                    right.and(
+                        |left| left.not(|inner| inner.var(Addr::new(i))),
+                        |right| right.not(|inner| inner.var(Addr::new(i + 1))),
+                    )
+                },
+            )
+        })
+    });
+    let mut models: Vec<Vec<bool>> = enumerate(&chain).collect();
+    models.sort();
+    assert_eq!(models, vec![vec![false; 100], vec![true; 100]]);
+}
+
+#[test]
+fn exact_counting() {
+    let mut rng = Rng::new(29);
+    for round in 0..60 {
+        let num_variables = 4 + round % 7;
+        let num_clauses = rng.below(5 * num_variables);
+        let cnf = random_cnf(&mut rng, num_variables, num_clauses);
+        let weights: Vec<[f64; 2]> = (0..num_variables)
+            .map(|_| [rng.next_f64(), rng.next_f64()])
+            .collect();
+
+        let mut count = 0u64;
+        let mut weighted = 0.0;
+        for x in 0..1usize << num_variables {
+            let assignment: Vec<bool> = (0..num_variables).map(|i| x & (1 << i) != 0).collect();
+            if cnf.eval(&assignment) {
+                count += 1;
+                weighted += assignment
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &value)| weights[i][value as usize])
+                    .product::<f64>();
+            }
+        }
+        assert_eq!(sharpsat::count(&cnf), BigUint::from(count));
+        let result: f64 =
+            sharpsat::weighted_count(&cnf, |id, neg| weights[id.addr()][!neg as usize]);
+        assert!((result - weighted).abs() < 1e-9);
+
+        let tree = cnf.to_tree();
+        assert_eq!(sharpsat::count_propositional(&tree), BigUint::from(count));
+        let result: f64 = sharpsat::weighted_count_propositional(&tree, |id, neg| {
+            weights[id.addr()][!neg as usize]
+        });
+        assert!((result - weighted).abs() < 1e-9);
+    }
+
+    // 40 independent copies of x∨y∨z decompose into components
+    let mut cnf = Cnf::new(120);
+    for i in 0..40 {
+        let clause: Vec<Literal> = (0..3)
+            .map(|j| Literal::new(Addr::new(3 * i + j), false))
+            .collect();
+        cnf.add_clause(&clause);
+    }
+    let mut expected = BigUint::from(1u64);
+    for _ in 0..40 {
+        expected *= &BigUint::from(7u64);
+    }
+    assert_eq!(sharpsat::count(&cnf), expected);
+
+    // the empty formula has one model, an empty clause none
+    let mut cnf = Cnf::new(0);
+    assert_eq!(sharpsat::count(&cnf), BigUint::from(1u64));
+    cnf.add_clause(&[]);
+    assert_eq!(sharpsat::count(&cnf), BigUint::default());
+}
+
+#[test]
+fn xor() {
+    for (num_variables, parity) in [(0, true), (1, true), (3, false), (7, true), (9, false)] {
+        let mut solver = Solver::new(num_variables);
+        let vars: Vec<Addr> = (0..num_variables).map(Addr::new).collect();
+        solver.add_xor(&vars, parity);
+        let mut count = 0;
+        while let Some(model) = solver.solve() {
+            let ones = model[..num_variables]
+                .iter()
+                .filter(|&&value| value)
+                .count();
+            assert_eq!(ones % 2 == 1, parity);
+            count += 1;
+            let blocking: Vec<Literal> = vars
+                .iter()
+                .map(|&id| Literal::new(id, model[id.addr()]))
+                .collect();
+            solver.add_clause(&blocking);
+        }
+        let expected = if num_variables == 0 {
+            !parity as usize
+        } else {
+            1 << (num_variables - 1)
+        };
+        assert_eq!(count, expected);
+    }
+}
+
+#[test]
+fn approximate_counting() {
+    let config = ApproxMc {
+        epsilon: 0.8,
+        delta: 0.5,
+        seed: 7,
+    };
+
+    let mut rng = Rng::new(31);
+    let cnf = random_cnf(&mut rng, 14, 20);
+    let exact = sharpsat::count(&cnf).to_f64();
+    let projection: Vec<Addr> = (0..14).map(Addr::new).collect();
+    let result = config.count(&cnf, &projection).unwrap();
+    assert!(!result.exact);
+    assert!(
+        result.lower_bound() <= exact && exact <= result.upper_bound(),
+        "{result} for {exact}"
+    );
+    assert_eq!(config.count(&cnf, &projection), Ok(result));
+
+    // few models are counted exactly, here through the Tseitin transform
+    let tree = PropositionalTree::build(|builder| {
+        builder.and(
+            |left| left.or(|left| left.var("A"), |right| right.var("B")),
+            |right| right.not(|inner| inner.var("C")),
+        )
+    });
+    let result = config.count_propositional(&tree).unwrap();
+    assert!(result.exact);
+    assert_eq!(result.estimate, BigUint::from(3u64));
+}
+
+#[test]
+fn uniform_sampling() {
+    let mut rng = Rng::new(43);
+    let cnf = random_cnf(&mut rng, 6, 8);
+    let mut expected: Vec<Vec<bool>> = naive::enumerate(&cnf.to_tree()).collect();
+    expected.sort();
+    assert!(expected.len() > 1);
+
+    let samples = sample_exact(&cnf, &mut rng, 4000).unwrap();
+    let mut frequencies: HashMap<Vec<bool>, usize> = Default::default();
+    for sample in samples {
+        *frequencies.entry(sample).or_default() += 1;
+    }
+    let mut seen: Vec<Vec<bool>> = frequencies.keys().cloned().collect();
+    seen.sort();
+    assert_eq!(seen, expected);
+    let mean = 4000.0 / expected.len() as f64;
+    for &frequency in frequencies.values() {
+        assert!(
+            (frequency as f64 - mean).abs() < 0.3 * mean,
+            "{frequency} for {mean}"
+        );
+    }
+    // the same seed gives the same samples
+    let first = sample_exact(&cnf, &mut Rng::new(1), 20).unwrap();
+    assert_eq!(sample_exact(&cnf, &mut Rng::new(1), 20).unwrap(), first);
+
+    let tree = PropositionalTree::build(|builder| {
+        builder.and(
+            |left| left.or(|left| left.var("A"), |right| right.var("B")),
+            |right| right.not(|inner| inner.var("C")),
+        )
+    });
+    let expected: Vec<Vec<bool>> = naive::enumerate(&tree).collect();
+    let sampler = UniGen::default();
+    for sample in sample_exact_propositional(&tree, &mut rng, 50).unwrap() {
+        assert!(expected.contains(&sample));
+    }
+    for sample in sampler.sample_propositional(&tree, &mut rng, 50).unwrap() {
+        assert!(expected.contains(&sample));
+    }
+
+    // enough models for the hashing to kick in
+    let cnf = random_cnf(&mut rng, 16, 12);
+    let projection: Vec<Addr> = (0..16).map(Addr::new).collect();
+    assert!(sharpsat::count(&cnf).to_f64() > 1000.0);
+    let samples = sampler.sample(&cnf, &projection, &mut rng, 20).unwrap();
+    assert_eq!(samples.len(), 20);
+    assert!(samples.iter().all(|sample| cnf.eval(sample)));
+
+    assert!(sample_exact(&pigeonhole(3), &mut rng, 1).is_err());
+    assert!(sampler.sample(&pigeonhole(3), &[], &mut rng, 1).is_err());
+    let sampler = UniGen {
+        epsilon: 1.0,
+        ..Default::default()
+    };
+    assert!(sampler.sample_propositional(&tree, &mut rng, 1).is_err());
+}
+
+#[test]
+fn maxsat() {
+    let mut rng = Rng::new(44);
+    for round in 0..100 {
+        let num_variables = 3 + round % 6;
+        let num_hard = rng.below(2 * num_variables);
+        let hard = random_cnf(&mut rng, num_variables, num_hard);
+        let mut wcnf = Wcnf::from_cnf(&hard);
+        for _ in 0..rng.below(4 * num_variables) {
+            let clause: Vec<Literal> = (0..1 + rng.below(3))
+                .map(|_| Literal::new(Addr::new(rng.below(num_variables)), rng.next_bool()))
+                .collect();
+            let weight = 1 + rng.below(if round % 2 == 0 { 1 } else { 10 }) as u64;
+            wcnf.add_soft(&clause, weight);
+        }
+
+        let optimum = (0..1usize << num_variables)
+            .map(|x| {
+                (0..num_variables)
+                    .map(|i| x & (1 << i) != 0)
+                    .collect::<Vec<bool>>()
+            })
+            .filter(|assignment| wcnf.is_feasible(assignment))
+            .map(|assignment| wcnf.cost(&assignment))
+            .min();
+        match (maxsat::solve(&wcnf), optimum) {
+            (Ok((assignment, cost)), Some(optimum)) => {
+                assert!(wcnf.is_feasible(&assignment));
+                assert_eq!(wcnf.cost(&assignment), cost);
+                assert_eq!(cost, optimum);
+            }
+            (Err(_), None) => {}
+            (result, optimum) => panic!("{result:?} for {optimum:?}"),
+        }
+    }
+
+    // at most 4 of the 5 pigeons fit, the empty clause always costs its weight
+    let mut wcnf = Wcnf::new(20);
+    let var = |pigeon: usize, hole: usize| Addr::new(4 * pigeon + hole);
+    for hole in 0..4 {
+        for a in 0..5 {
+            for b in a + 1..5 {
+                wcnf.add_hard(&[
+                    Literal::new(var(a, hole), true),
+                    Literal::new(var(b, hole), true),
+                ]);
+            }
+        }
+    }
+    for pigeon in 0..5 {
+        let clause: Vec<Literal> = (0..4)
+            .map(|hole| Literal::new(var(pigeon, hole), false))
+            .collect();
+        wcnf.add_soft(&clause, 2 + pigeon as u64);
+    }
+    wcnf.add_soft(&[], 7);
+    assert_eq!(maxsat::solve(&wcnf).unwrap().1, 9);
+
+    assert!(maxsat::solve(&Wcnf::from_cnf(&pigeonhole(3))).is_err());
+}
+
+// x + y + z = 2 over integers
+struct Sum;
+
+impl Eval<i64> for Sum {
+    type Output = bool;
+
+    fn eval(&self, assignment: &Vec<i64>) -> bool {
+        assignment.iter().sum::<i64>() == 2
+    }
+}
+
+#[test]
+fn domains() {
+    let ranges = vec![
+        Range { start: -1, end: 2 },
+        Range { start: 0, end: 3 },
+        Range { start: 1, end: 2 },
+    ];
+    let solutions: Vec<Vec<i64>> = naive::enumerate_domains(&Sum, ranges.clone()).collect();
+    assert_eq!(
+        solutions,
+        vec![vec![1, 0, 1], vec![0, 1, 1], vec![-1, 2, 1]]
+    );
+    assert_eq!(
+        naive::count_domains(&Sum, ranges.clone()),
+        BigUint::from(3u64)
+    );
+    let enumerate = naive::enumerate_domains(&Sum, ranges);
+    assert_eq!(enumerate.domain_size(), BigUint::from(9u64));
+
+    let colours = Categorical {
+        labels: vec!["red".to_string(), "green".to_string(), "blue".to_string()],
+    };
+    assert_eq!(colours.label(2), "blue");
+    let cards = [3, 2, 5];
+    let domains: Vec<Integer> = cards
+        .iter()
+        .map(|&card| Integer {
+            vars: Default::default(),
+            card,
+        })
+        .collect();
+
+    // v0 = 1 -> v1 = 0, v2 != 3 and v2 != 0 ∨ v0 != 2
+    let holds = |values: &[usize]| {
+        (values[0] != 1 || values[1] == 0) && values[2] != 3 && (values[2] != 0 || values[0] != 2)
+    };
+    let mut expected: Vec<Vec<usize>> = Default::default();
+    for x in 0..cards.iter().product() {
+        let values = vec![x % 3, x / 3 % 2, x / 6];
+        if holds(&values) {
+            expected.push(values);
+        }
+    }
+    expected.sort();
+
+    for encoding in [DomainEncoding::OneHot, DomainEncoding::Log] {
+        let lowering = Lowering::new(&domains, encoding);
+        let mut cnf = Cnf::new(lowering.num_variables());
+        let not = |var: usize, value: usize| -> Vec<Literal> {
+            lowering
+                .literals(var, value)
+                .into_iter()
+                .map(|lit| !lit)
+                .collect()
+        };
+        let mut clause = not(0, 1);
+        clause.extend(lowering.literals(1, 0));
+        cnf.add_clause(&clause);
+        cnf.add_clause(&not(2, 3));
+        let mut clause = not(2, 0);
+        clause.extend(not(0, 2));
+        cnf.add_clause(&clause);
+
+        let mut models: Vec<Vec<usize>> = lowering.models(&cnf).collect();
+        models.sort();
+        assert_eq!(models, expected);
+        assert_eq!(lowering.count(&cnf), BigUint::from(expected.len() as u64));
+
+        let tree = cnf.to_tree();
+        let mut solutions: Vec<Vec<usize>> =
+            naive::enumerate_domains(&lowering.lower(&tree), domains.clone()).collect();
+        solutions.sort();
+        assert_eq!(solutions, expected);
+        for values in expected.iter() {
+            assert_eq!(
+                lowering.decode(&lowering.encode(values)).as_ref(),
+                Some(values)
+            );
+        }
+    }
+}
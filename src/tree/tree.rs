@@ -100,6 +100,55 @@ where
         self.mapping = source.mapping.clone();
     }
 
+    // same structure and names, every node value mapped through f
+    pub fn map<U: Copy + Debug + PartialEq, F: Fn(T) -> U>(&self, f: F) -> Tree<U, MAX_CHILDS> {
+        Tree {
+            named: self.named.clone(),
+            mapping: self.mapping.clone(),
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| NodeValue {
+                    node: node.node,
+                    value: f(node.value),
+                })
+                .collect(),
+            output: self.output,
+        }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // nodes reachable from the output, every child before its parents
+    pub fn topological(&self) -> Vec<Addr> {
+        let mut order: Vec<Addr> = Default::default();
+        if self.output.is_none() {
+            return order;
+        }
+
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack: Vec<(Addr, bool)> = vec![(self.output, false)];
+        while let Some((idx, expanded)) = stack.pop() {
+            if expanded {
+                order.push(idx);
+                continue;
+            }
+            if visited[idx.addr()] {
+                continue;
+            }
+            visited[idx.addr()] = true;
+            stack.push((idx, true));
+            for &child in self[idx].node.operands().iter().rev() {
+                if child.is_addr() && !visited[child.addr()] {
+                    stack.push((child, false));
+                }
+            }
+        }
+        order
+    }
+
     /*fn replace<B: Fn(IndexedMutRef<NodeRecycler<T,MAX_CHILDS>>) -> Addr>(
         &mut self,
         from_node: Addr,